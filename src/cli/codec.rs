@@ -0,0 +1,232 @@
+use crate::cli::base64::Base64Format;
+use crate::process::base64::{
+    data_uri_output_path, process_decode, process_decode_auto, process_decode_data_uri,
+    process_encode, process_encode_data_uri,
+};
+use crate::process::codec::{process_codec_decode, process_codec_encode};
+use crate::utils::{get_reader, get_writer};
+use crate::CmdExecutor;
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+const FORMAT_HELP: &str = "Format: standard, standard-nopad, urlsafe, urlsafe-pad or mime base64, base32, base58, hex or ascii85";
+const DECODE_FORMAT_HELP: &str = "Format: standard, standard-nopad, urlsafe, urlsafe-pad or mime base64, base32, base58, hex or ascii85, auto detects the base64 format from the input";
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum CodecSubCommand {
+    #[command(about = "Encode input with the given format")]
+    Encode(CodecEncodeOpts),
+    #[command(about = "Decode input with the given format")]
+    Decode(CodecDecodeOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CodecEncodeOpts {
+    #[arg(short, long, value_parser = super::check_input, default_value = "-", help = "Input file to encode"
+    )]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "Output file to write the encoded text to"
+    )]
+    pub output: String,
+    #[arg(long, value_parser = EncodingFormat::from_str, default_value = "standard", help = FORMAT_HELP)]
+    pub format: EncodingFormat,
+    #[arg(
+        long,
+        help = "Wrap encoded lines after N characters, 0 disables wrapping"
+    )]
+    pub wrap: Option<usize>,
+    #[arg(
+        long,
+        conflicts_with_all = ["wrap", "format"],
+        help = "Emit a base64 data URI with the MIME type sniffed from the input"
+    )]
+    pub data_uri: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CodecDecodeOpts {
+    #[arg(short, long, value_parser = super::check_input, default_value = "-", help = "Input file to decode"
+    )]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        help = "Output file to write the decoded bytes to, - for stdout [default: stdout, or the input name with the payload extension for data URIs]"
+    )]
+    pub output: Option<String>,
+    #[arg(long, value_parser = DecodeFormat::from_str, default_value = "standard", help = DECODE_FORMAT_HELP)]
+    pub format: DecodeFormat,
+    #[arg(
+        long,
+        help = "Ignore whitespace and line breaks anywhere in base64 input, the other formats always do"
+    )]
+    pub lenient: bool,
+    #[arg(
+        long,
+        conflicts_with = "format",
+        help = "Decode a data URI and write its payload to a file with a matching extension"
+    )]
+    pub data_uri: bool,
+}
+
+/// Any format of the encode/decode commands, base64 variants keep their historical names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingFormat {
+    Base64(Base64Format),
+    Codec(CodecFormat),
+}
+
+/// Format of the decode command, which can also detect the base64 format from the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeFormat {
+    Auto,
+    Format(EncodingFormat),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecFormat {
+    Base32,
+    Base58,
+    Hex,
+    Ascii85,
+}
+
+impl From<CodecFormat> for &str {
+    fn from(format: CodecFormat) -> &'static str {
+        match format {
+            CodecFormat::Base32 => "base32",
+            CodecFormat::Base58 => "base58",
+            CodecFormat::Hex => "hex",
+            CodecFormat::Ascii85 => "ascii85",
+        }
+    }
+}
+
+impl FromStr for CodecFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "base32" => Ok(CodecFormat::Base32),
+            "base58" => Ok(CodecFormat::Base58),
+            "hex" => Ok(CodecFormat::Hex),
+            "ascii85" => Ok(CodecFormat::Ascii85),
+            _ => Err(anyhow::anyhow!("Not supported format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for CodecFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+impl FromStr for EncodingFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Base64Format::from_str(s) {
+            Ok(format) => Ok(EncodingFormat::Base64(format)),
+            Err(_) => Ok(EncodingFormat::Codec(CodecFormat::from_str(s)?)),
+        }
+    }
+}
+
+impl FromStr for DecodeFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(DecodeFormat::Auto),
+            _ => Ok(DecodeFormat::Format(EncodingFormat::from_str(s)?)),
+        }
+    }
+}
+
+impl CmdExecutor for CodecEncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reader = get_reader(&self.input)?;
+        let writer = get_writer(&self.output)?;
+        match self.format {
+            _ if self.data_uri => {
+                process_encode_data_uri(reader, writer, &self.input)?;
+            }
+            EncodingFormat::Base64(format) => {
+                process_encode(reader, writer, format, self.wrap)?;
+            }
+            EncodingFormat::Codec(format) => {
+                process_codec_encode(reader, writer, format, self.wrap)?;
+            }
+        }
+        if self.output == "-" {
+            println!();
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CodecDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reader = get_reader(&self.input)?;
+        if self.data_uri {
+            // parse the URI first, so a malformed one leaves no empty output file behind
+            let uri = process_decode_data_uri(reader)?;
+            let output = match self.output {
+                Some(output) => output,
+                None => data_uri_output_path(&self.input, &uri)
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            get_writer(&output)?.write_all(&uri.payload)?;
+            eprintln!("MIME type: {}", uri.mime);
+            if output != "-" {
+                eprintln!("Payload written to {}", output);
+            }
+            return Ok(());
+        }
+
+        if self.lenient && self.format == DecodeFormat::Auto {
+            return Err(anyhow::anyhow!(
+                "--lenient cannot be used with --format auto"
+            ));
+        }
+        let writer = get_writer(self.output.as_deref().unwrap_or("-"))?;
+        match self.format {
+            DecodeFormat::Auto => {
+                let (format, _) = process_decode_auto(reader, writer)?;
+                eprintln!("Detected base64 format: {}", <&str>::from(format));
+            }
+            DecodeFormat::Format(EncodingFormat::Base64(format)) => {
+                process_decode(reader, writer, format, self.lenient)?;
+            }
+            DecodeFormat::Format(EncodingFormat::Codec(format)) => {
+                process_codec_decode(reader, writer, format)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_is_decode_only() {
+        assert_eq!(DecodeFormat::from_str("auto").unwrap(), DecodeFormat::Auto);
+        assert_eq!(
+            DecodeFormat::from_str("hex").unwrap(),
+            DecodeFormat::Format(EncodingFormat::Codec(CodecFormat::Hex))
+        );
+        assert!(EncodingFormat::from_str("auto").is_err());
+    }
+}
@@ -1,9 +1,13 @@
-use crate::process::gen_pass::generate_password;
+use crate::process::gen_pass::{generate_password, self_test};
 use crate::CmdExecutor;
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenPassOpts {
+    #[command(subcommand)]
+    pub command: Option<GenPassSubCommand>,
     #[arg(short, long, default_value = "16", help = "Password length")]
     pub length: u8,
     #[arg(long, default_value = "true", help = "Include lowercase letters")]
@@ -16,8 +20,34 @@ pub struct GenPassOpts {
     pub special: bool,
 }
 
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum GenPassSubCommand {
+    #[command(about = "Check that generated passwords are uniformly distributed")]
+    SelfTest(GenPassSelfTestOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassSelfTestOpts {
+    #[arg(
+        short,
+        long,
+        default_value = "10000",
+        help = "Number of passwords to generate"
+    )]
+    pub samples: usize,
+    #[arg(short, long, default_value = "16", help = "Password length")]
+    pub length: u8,
+    #[arg(long, help = "Seed a deterministic rng instead of using the OS rng")]
+    pub seed: Option<u64>,
+}
+
 impl CmdExecutor for GenPassOpts {
     async fn execute(self) -> anyhow::Result<()> {
+        if let Some(command) = self.command {
+            return command.execute().await;
+        }
+
         let pass = generate_password(
             self.length,
             self.lowercase,
@@ -31,3 +61,27 @@ impl CmdExecutor for GenPassOpts {
         Ok(())
     }
 }
+
+impl CmdExecutor for GenPassSelfTestOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let report = self_test(self.samples, self.length, self.seed)?;
+        println!("{:<10} {:>12} {:>14}", "class", "observed", "expected");
+        for class in &report.classes {
+            println!(
+                "{:<10} {:>12} {:>14.1}",
+                class.name, class.observed, class.expected
+            );
+        }
+        println!(
+            "chi-square: {:.2} (df = {}, critical = {:.2})",
+            report.chi_square, report.degrees_of_freedom, report.critical_value
+        );
+        if !report.passed() {
+            return Err(anyhow::anyhow!(
+                "Self test failed: character distribution is not uniform"
+            ));
+        }
+        println!("Self test passed");
+        Ok(())
+    }
+}
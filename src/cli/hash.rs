@@ -0,0 +1,205 @@
+use crate::cli::codec::CodecFormat;
+use crate::process::codec::encode_bytes;
+use crate::process::hash::{format_checksum_line, hash_files, process_check, CheckStatus};
+use crate::CmdExecutor;
+use clap::Parser;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Parser)]
+pub struct HashOpts {
+    #[arg(value_parser = super::check_input, default_value = "-", help = "Files to hash, or checksum manifests with --check"
+    )]
+    pub files: Vec<String>,
+    #[arg(short, long, value_parser = HashAlgorithm::from_str, default_value = "sha256", help = "Hash algorithm: blake3, sha256, sha512, sha3-256, sha3-512, xxh64, xxh3 or xxh3-128"
+    )]
+    pub algo: HashAlgorithm,
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Output length in bytes for the blake3 extendable output, up to 65535"
+    )]
+    pub length: Option<u16>,
+    #[arg(
+        short,
+        long,
+        help = "Verify the checksums listed in the given manifests"
+    )]
+    pub check: bool,
+    #[arg(short, long, help = "With --check, only report files that fail")]
+    pub quiet: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Blake3,
+    Sha256,
+    Sha512,
+    Sha3_256,
+    Sha3_512,
+    Xxh64,
+    Xxh3,
+    Xxh3_128,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            "sha3-256" => Ok(HashAlgorithm::Sha3_256),
+            "sha3-512" => Ok(HashAlgorithm::Sha3_512),
+            "xxh64" => Ok(HashAlgorithm::Xxh64),
+            "xxh3" => Ok(HashAlgorithm::Xxh3),
+            "xxh3-128" => Ok(HashAlgorithm::Xxh3_128),
+            _ => Err(anyhow::anyhow!("Not supported hash algorithm: {}", s)),
+        }
+    }
+}
+
+impl From<HashAlgorithm> for &'static str {
+    fn from(algo: HashAlgorithm) -> Self {
+        match algo {
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Sha3_256 => "sha3-256",
+            HashAlgorithm::Sha3_512 => "sha3-512",
+            HashAlgorithm::Xxh64 => "xxh64",
+            HashAlgorithm::Xxh3 => "xxh3",
+            HashAlgorithm::Xxh3_128 => "xxh3-128",
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+impl CmdExecutor for HashOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if self.length.is_some() && self.algo != HashAlgorithm::Blake3 {
+            return Err(anyhow::anyhow!("--length is only supported for blake3"));
+        }
+        if self.check {
+            return check(&self);
+        }
+
+        let mut failed = 0;
+        for (file, digest) in self.files.iter().zip(hash_files(
+            &self.files,
+            self.algo,
+            self.length.map(usize::from),
+        )) {
+            match digest {
+                Ok(digest) => {
+                    let hex = String::from_utf8(encode_bytes(&digest, CodecFormat::Hex))?;
+                    println!("{}", format_checksum_line(&hex, file));
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", file, e);
+                    failed += 1;
+                }
+            }
+        }
+        match failed {
+            0 => Ok(()),
+            n => Err(anyhow::anyhow!("{} file(s) could not be read", n)),
+        }
+    }
+}
+
+fn check(opts: &HashOpts) -> anyhow::Result<()> {
+    let (mut mismatched, mut unreadable, mut malformed, mut unchecked) = (0, 0, 0, 0);
+    for manifest in &opts.files {
+        // like sha256sum -c, a bad manifest is reported and the next ones are still checked
+        let report = match process_check(manifest, opts.algo) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("{}", e);
+                unchecked += 1;
+                continue;
+            }
+        };
+        malformed += report.malformed;
+        for (name, status) in report.entries {
+            match status {
+                CheckStatus::Ok if opts.quiet => {}
+                CheckStatus::Ok => println!("{}: OK", name),
+                CheckStatus::Mismatch => {
+                    println!("{}: FAILED", name);
+                    mismatched += 1;
+                }
+                CheckStatus::Unreadable(e) => {
+                    eprintln!("{}: {}", name, e);
+                    println!("{}: FAILED open or read", name);
+                    unreadable += 1;
+                }
+            }
+        }
+    }
+
+    if malformed > 0 {
+        eprintln!("WARNING: {} line(s) are improperly formatted", malformed);
+    }
+    if unreadable > 0 {
+        eprintln!("WARNING: {} listed file(s) could not be read", unreadable);
+    }
+    if mismatched > 0 {
+        eprintln!("WARNING: {} computed checksum(s) did NOT match", mismatched);
+    }
+    match (mismatched + unreadable, unchecked) {
+        (0, 0) => Ok(()),
+        (n, 0) => Err(anyhow::anyhow!("{} file(s) failed verification", n)),
+        (n, m) => Err(anyhow::anyhow!(
+            "{} file(s) failed verification, {} manifest(s) could not be checked",
+            n,
+            m
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_is_bounded() {
+        for length in ["0", "65536", "18446744073709551615"] {
+            assert!(
+                HashOpts::try_parse_from(["hash", "-a", "blake3", "--length", length]).is_err()
+            );
+        }
+        let opts = HashOpts::try_parse_from(["hash", "-a", "blake3", "--length", "64"]).unwrap();
+        assert_eq!(opts.length, Some(64));
+    }
+
+    #[test]
+    fn test_check_continues_after_bad_manifest() {
+        let dir = std::env::temp_dir().join(format!("rcli-hash-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let empty = dir.join("empty.sha256");
+        let sums = dir.join("sums.sha256");
+        std::fs::write(&empty, "not a checksum line\n").unwrap();
+        std::fs::write(&sums, format!("{}  Cargo.toml\n", "00".repeat(32))).unwrap();
+        let opts = HashOpts::try_parse_from([
+            "hash",
+            "--check",
+            empty.to_str().unwrap(),
+            sums.to_str().unwrap(),
+        ])
+        .unwrap();
+        let err = check(&opts).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "1 file(s) failed verification, 1 manifest(s) could not be checked"
+        );
+    }
+}
@@ -1,14 +1,34 @@
-use crate::process::jwt::{process_jwt_sign, process_jwt_verify};
+use crate::cli::check_input;
+use crate::process::jwt::{
+    format_duration, generate_jti, parse_claim, process_jwt_decode, process_jwt_sign,
+    process_jwt_verify, JwtClaims, JwtPolicy,
+};
+use crate::process::verify::{VerifyFailure, VerifyReport, WithFailure, EXIT_CODES_HELP};
+use crate::utils::get_reader;
 use crate::CmdExecutor;
-use chrono::Local;
+use chrono::{Local, Utc};
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExecutor)]
 pub enum JwtSubCommand {
     Sign(JwtSignOpts),
+    #[command(after_help = EXIT_CODES_HELP)]
     Verify(JwtVerifyOpts),
+    #[command(about = "Show the header and claims of a token without verifying it")]
+    Decode(JwtDecodeOpts),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    Hs256,
+    Rs256,
+    Es256,
+    EdDsa,
 }
 
 #[derive(Debug, Parser)]
@@ -17,23 +37,122 @@ pub struct JwtSignOpts {
     pub aud: String,
     #[arg(short, long)]
     pub sub: String,
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        help = "Expire after this long, e.g. 14d, 1h, 30m or seconds"
+    )]
     pub exp: String,
-    #[arg(short, long)]
+    #[arg(long, help = "Issuer")]
+    pub iss: Option<String>,
+    #[arg(
+        long,
+        help = "Not valid before this long from now, same format as --exp"
+    )]
+    pub nbf: Option<String>,
+    #[arg(long, num_args = 0..=1, help = "Token id, a random one when no value is given")]
+    pub jti: Option<Option<String>>,
+    #[arg(long, value_parser = parse_claim, help = "Custom claim as name=value, the value is parsed as JSON when possible, e.g. admin=true or roles='[\"a\"]'. Registered claims (iss, sub, aud, exp, nbf, iat, jti) must be set with their own options")]
+    pub claim: Vec<(String, Value)>,
+    #[arg(long, value_parser = check_input, help = "JSON object of claims, the other options take precedence")]
+    pub claims_file: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "HS256 secret, or the secret key file (PEM or DER) of the other algorithms"
+    )]
     pub key: String,
+    #[arg(long, value_parser = JwtAlgorithm::from_str, default_value = "HS256", help = "Signing algorithm: HS256, RS256, ES256 or EdDSA")]
+    pub alg: JwtAlgorithm,
 }
 
 #[derive(Debug, Parser)]
 pub struct JwtVerifyOpts {
     #[arg(short, long)]
     pub token: String,
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        help = "HS256 secret, or the public key file (PEM or DER) of the other algorithms"
+    )]
     pub key: String,
+    #[arg(long, value_parser = JwtAlgorithm::from_str, value_delimiter = ',', default_value = "HS256", help = "Allowed algorithm, repeat or separate with commas to allow several, HS256 cannot be combined with the others")]
+    pub alg: Vec<JwtAlgorithm>,
+    #[arg(long, help = "Expected audience, repeat to accept any of several")]
+    pub aud: Vec<String>,
+    #[arg(long, help = "Expected issuer, repeat to accept any of several")]
+    pub iss: Vec<String>,
+    #[arg(long, default_value_t = JwtPolicy::DEFAULT_LEEWAY, help = "Seconds of clock skew tolerated when checking exp and nbf")]
+    pub leeway: u64,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Claim that must be present, repeat or separate with commas"
+    )]
+    pub require: Vec<String>,
+    #[arg(long, help = "Print the result and the claims as a JSON object")]
+    pub json: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct JwtDecodeOpts {
+    #[arg(short, long)]
+    pub token: String,
+}
+
+impl FromStr for JwtAlgorithm {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "HS256" => Ok(Self::Hs256),
+            "RS256" => Ok(Self::Rs256),
+            "ES256" => Ok(Self::Es256),
+            "EDDSA" => Ok(Self::EdDsa),
+            _ => Err("Invalid JWT algorithm"),
+        }
+    }
+}
+
+impl From<JwtAlgorithm> for &'static str {
+    fn from(alg: JwtAlgorithm) -> Self {
+        match alg {
+            JwtAlgorithm::Hs256 => "HS256",
+            JwtAlgorithm::Rs256 => "RS256",
+            JwtAlgorithm::Es256 => "ES256",
+            JwtAlgorithm::EdDsa => "EdDSA",
+        }
+    }
+}
+
+impl fmt::Display for JwtAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
 }
 
 impl CmdExecutor for JwtSignOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let jwt = process_jwt_sign(&self.aud, &self.sub, &self.exp, &self.key)?;
+        let mut claims = match &self.claims_file {
+            Some(path) => JwtClaims::from_reader(get_reader(path)?)?,
+            None => JwtClaims::default(),
+        };
+        for (name, value) in self.claim {
+            claims.insert(name, value);
+        }
+        claims.insert("aud", self.aud);
+        claims.insert("sub", self.sub);
+        claims.set_validity(&self.exp)?;
+        if let Some(iss) = self.iss {
+            claims.insert("iss", iss);
+        }
+        if let Some(nbf) = &self.nbf {
+            claims.set_not_before(nbf)?;
+        }
+        if let Some(jti) = self.jti {
+            claims.insert("jti", jti.unwrap_or_else(generate_jti));
+        }
+        let jwt = process_jwt_sign(&claims, &self.key, self.alg)?;
         println!("{}", jwt);
         Ok(())
     }
@@ -41,24 +160,71 @@ impl CmdExecutor for JwtSignOpts {
 
 impl CmdExecutor for JwtVerifyOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        match process_jwt_verify(&self.token, &self.key) {
-            Ok(claim) => {
-                println!(
-                    "Token is valid, sub: {}, aud: {}, iss: {}, exp: {}",
-                    claim.sub,
-                    claim.aud,
-                    chrono::DateTime::from_timestamp(claim.iat as i64, 0)
-                        .unwrap()
-                        .with_timezone(&Local),
-                    chrono::DateTime::from_timestamp(claim.exp as i64, 0)
-                        .unwrap()
-                        .with_timezone(&Local)
-                )
-            }
-            Err(_) => {
-                println!("Token is invalid")
-            }
+        let policy = JwtPolicy {
+            algorithms: self.alg,
+            audiences: self.aud,
+            issuers: self.iss,
+            leeway: self.leeway,
+            required: self.require,
+        };
+        // every error carries its cause, so the exit code matches the one of the JSON report
+        let result = process_jwt_verify(&self.token, &self.key, &policy)
+            .with_failure(VerifyFailure::MalformedInput);
+        if self.json {
+            let report = match &result {
+                Ok(claims) => VerifyReport::verified(Some(serde_json::to_value(claims)?)),
+                Err(e) => VerifyReport::failed(e),
+            };
+            println!("{}", serde_json::to_string(&report)?);
+            return result.map(|_| ());
+        }
+
+        let claims = result?;
+        println!("Token is valid");
+        print_claims(&claims)?;
+        Ok(())
+    }
+}
+
+impl CmdExecutor for JwtDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let (header, claims) = process_jwt_decode(&self.token)?;
+        println!("Header:\n{}", serde_json::to_string_pretty(&header)?);
+        println!("Payload:");
+        print_claims(&claims)?;
+
+        let now = Utc::now();
+        if let Some(exp) = claims.time("exp").filter(|exp| *exp <= now) {
+            eprintln!(
+                "Warning: the token has expired at {}",
+                exp.with_timezone(&Local)
+            );
         }
+        if let Some(nbf) = claims.time("nbf").filter(|nbf| *nbf > now) {
+            eprintln!(
+                "Warning: the token is not valid before {}",
+                nbf.with_timezone(&Local)
+            );
+        }
+        eprintln!("Note: the signature was NOT verified, use `rcli jwt verify` to check it");
         Ok(())
     }
 }
+
+/// Print the claims as JSON, followed by the numeric dates in local time and the time left before `exp`
+fn print_claims(claims: &JwtClaims) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(claims)?);
+    for name in ["iat", "nbf", "exp"] {
+        if let Some(time) = claims.time(name) {
+            println!("{}: {}", name, time.with_timezone(&Local));
+        }
+    }
+    if let Some(exp) = claims.time("exp") {
+        let left = (exp - Utc::now()).num_seconds();
+        match left > 0 {
+            true => println!("Expires in {}", format_duration(left as u64)),
+            false => println!("Expired {} ago", format_duration(left.unsigned_abs())),
+        }
+    }
+    Ok(())
+}
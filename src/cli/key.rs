@@ -0,0 +1,146 @@
+use crate::cli::check_input;
+use crate::cli::text::KeyFormat;
+use crate::process::key_encryption::write_secret_key_file;
+use crate::process::keyring::Keyring;
+use crate::process::text::{key_file_fingerprint, KEY_ID_LEN};
+use crate::utils::get_writer;
+use crate::CmdExecutor;
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::io::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum KeySubCommand {
+    #[command(about = "List the keys of the keyring")]
+    List(KeyListOpts),
+    #[command(about = "Copy a key pair or a single key file into the keyring")]
+    Import(KeyImportOpts),
+    #[command(about = "Write a keyring key to a file")]
+    Export(KeyExportOpts),
+    #[command(about = "Remove a key from the keyring")]
+    Delete(KeyDeleteOpts),
+    #[command(about = "Show the BLAKE3 fingerprint of a keyring key or a key file")]
+    Fingerprint(KeyFingerprintOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyListOpts {}
+
+#[derive(Debug, Parser)]
+pub struct KeyImportOpts {
+    #[arg(short, long, help = "Unique name of the key in the keyring")]
+    pub name: String,
+    #[arg(long, value_parser = KeyFormat::from_str)]
+    pub format: KeyFormat,
+    #[arg(long, value_parser = check_input, required_unless_present = "public", help = "Secret key file, copied as is so encrypted keys stay encrypted"
+    )]
+    pub secret: Option<String>,
+    #[arg(long, value_parser = check_input, help = "Public key file")]
+    pub public: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyExportOpts {
+    #[arg(help = "Key name or key id")]
+    pub key: String,
+    #[arg(long, help = "Export the secret key instead of the public key")]
+    pub secret: bool,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyDeleteOpts {
+    #[arg(help = "Key name or key id")]
+    pub key: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyFingerprintOpts {
+    #[arg(
+        required_unless_present = "file",
+        conflicts_with = "file",
+        help = "Key name or key id"
+    )]
+    pub key: Option<String>,
+    #[arg(long, value_parser = check_input, requires = "format", help = "Fingerprint a key file instead of a keyring key")]
+    pub file: Option<String>,
+    #[arg(long, value_parser = KeyFormat::from_str)]
+    pub format: Option<KeyFormat>,
+    #[arg(long, requires = "file", help = "The key file holds a secret key")]
+    pub secret: bool,
+}
+
+impl CmdExecutor for KeyListOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        for entry in Keyring::open_default()?.list()? {
+            let halves = match (entry.has_secret, entry.has_public) {
+                (true, true) => "secret+public",
+                (true, false) => "secret",
+                _ => "public",
+            };
+            println!(
+                "{}  {:<16}  {:<14}  {}  {}",
+                entry.key_id(),
+                entry.algorithm,
+                halves,
+                entry.created,
+                entry.name
+            );
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for KeyImportOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let entry = Keyring::open_default()?.import(
+            &self.name,
+            self.format,
+            self.secret.as_deref(),
+            self.public.as_deref(),
+        )?;
+        println!("Imported {} as {}", entry.name, entry.key_id());
+        Ok(())
+    }
+}
+
+impl CmdExecutor for KeyExportOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let keyring = Keyring::open_default()?;
+        let entry = keyring.find(&self.key)?;
+        let data = match self.secret {
+            true => std::fs::read(keyring.secret_path(&entry)?)?,
+            false => std::fs::read(keyring.public_path(&entry)?)?,
+        };
+        match self.output.as_str() {
+            "-" => get_writer(&self.output)?.write_all(&data)?,
+            path if self.secret => write_secret_key_file(path, &data)?,
+            path => std::fs::write(path, &data)?,
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for KeyDeleteOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let entry = Keyring::open_default()?.delete(&self.key)?;
+        println!("Deleted {} ({})", entry.name, entry.key_id());
+        Ok(())
+    }
+}
+
+impl CmdExecutor for KeyFingerprintOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let fingerprint = match (&self.key, &self.file, self.format) {
+            (Some(key), _, _) => Keyring::open_default()?.find(key)?.fingerprint,
+            (None, Some(file), Some(format)) => key_file_fingerprint(file, format, self.secret)?,
+            _ => unreachable!("clap requires a key or --file with --format"),
+        };
+        println!("Key id:      {}", &fingerprint[..KEY_ID_LEN]);
+        println!("Fingerprint: {}", fingerprint);
+        Ok(())
+    }
+}
@@ -2,10 +2,13 @@ use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
 pub mod base64;
+pub mod codec;
 pub mod csv;
 pub mod gen_pass;
+pub mod hash;
 pub mod http;
 pub mod jwt;
+pub mod key;
 pub mod text;
 #[derive(Debug, Parser)]
 #[command(name="rcli", author, version, about, long_about = None)]
@@ -21,8 +24,19 @@ pub enum SubCommand {
     Csv(csv::CsvOpts),
     #[command(about = "Generate a custom password")]
     GenPass(gen_pass::GenPassOpts),
-    #[command(subcommand, about = "Base64 encode/decode")]
-    Base64(base64::Base64SubCommand),
+    #[command(
+        subcommand,
+        visible_alias = "base64",
+        about = "Base64/base32/base58/hex/ascii85 encode/decode"
+    )]
+    Codec(codec::CodecSubCommand),
+    #[command(about = "Hash files with blake3, sha2, sha3 or xxhash")]
+    Hash(hash::HashOpts),
+    #[command(
+        subcommand,
+        about = "Manage the local keyring, stored in $RCLI_KEYRING or ~/.rcli/keyring"
+    )]
+    Key(key::KeySubCommand),
     #[command(subcommand, about = "Text sign/verify")]
     Text(text::TextSubCommand),
     #[command(subcommand, about = "HTTP static file server")]
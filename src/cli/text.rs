@@ -1,21 +1,55 @@
 use crate::cli::check_input;
-use crate::process::text::{generate_key, process_sign, process_verify, Key};
+use crate::cli::codec::CodecFormat;
+use crate::process::codec::encode_bytes;
+use crate::process::encryption::{process_decrypt, process_encrypt};
+use crate::process::envelope::{process_sign_envelope, process_verify_envelope, SignatureEnvelope};
+use crate::process::key_encryption::{
+    encrypt_key, read_new_passphrase, read_secret_key_file, write_secret_key_file,
+};
+use crate::process::key_format::{decode_x25519_public, decode_x25519_secret};
+use crate::process::keyring::Keyring;
+use crate::process::manifest::{process_sign_dir, process_verify_dir, SignedManifest};
+use crate::process::minisign::MinisignSignature;
+use crate::process::seal::{process_open, process_seal};
+use crate::process::text::{
+    convert_ecdsa_signature, decode_signature, encode_signature, generate_key,
+    generate_minisign_key, process_sign, process_sign_minisign, process_verify, Blake3, Key,
+};
+use crate::process::verify::{
+    VerifyError, VerifyFailure, VerifyReport, WithFailure, EXIT_CODES_HELP,
+};
+use crate::utils::{get_reader, get_writer};
 use crate::CmdExecutor;
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 use std::fmt;
+use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+const CONTEXT_HELP: &str = "Derive the blake3 key from the key file content with this context, keys of any length are accepted";
+
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExecutor)]
 pub enum TextSubCommand {
     #[command(about = "Sign a text")]
     Sign(TextSignOpts),
-    #[command(about = "Verify a signed text")]
+    #[command(about = "Verify a signed text", after_help = EXIT_CODES_HELP)]
     Verify(TextVerifyOpts),
     #[command(about = "Generate a key for signing")]
     GenerateKey(TextGenerateKeyOpts),
+    #[command(about = "Encrypt a text with a blake3 key")]
+    Encrypt(TextEncryptOpts),
+    #[command(about = "Decrypt a text encrypted with a blake3 key")]
+    Decrypt(TextDecryptOpts),
+    #[command(about = "Encrypt a text to one or more x25519 public keys")]
+    Seal(TextSealOpts),
+    #[command(about = "Decrypt a sealed text with an x25519 secret key")]
+    Open(TextOpenOpts),
+    #[command(about = "Sign a manifest of every file in a directory with an ed25519 key")]
+    SignDir(TextSignDirOpts),
+    #[command(about = "Verify a directory against a signed manifest")]
+    VerifyDir(TextVerifyDirOpts),
 }
 
 #[derive(Debug, Parser)]
@@ -24,8 +58,34 @@ pub struct TextSignOpts {
     pub input: String,
     #[arg(short, long, value_parser = check_input, default_value = "-")]
     pub key: String,
-    #[arg(long, value_parser= TextSignFormat::from_str, default_value = "blake3")]
-    pub format: TextSignFormat,
+    #[arg(
+        long,
+        conflicts_with = "key",
+        help = "Sign with a keyring key, by name or key id"
+    )]
+    pub key_id: Option<String>,
+    #[arg(long, value_parser = TextSignFormat::from_str, help = "Signature format [default: blake3, or the algorithm of the --key-id key]"
+    )]
+    pub format: Option<TextSignFormat>,
+    #[arg(long, value_parser = SigEncoding::from_str, help = "Signature encoding: hex, base64, base64url or raw [default: hex for hmac formats, base64url otherwise]"
+    )]
+    pub sig_encoding: Option<SigEncoding>,
+    #[arg(long, help = "Write the signature to a detached signature file")]
+    pub sig_out: Option<PathBuf>,
+    #[arg(long, value_parser = EnvelopeFormat::from_str, conflicts_with = "sig_encoding", help = "Wrap the signature in an envelope recording algorithm, key id and time: json or compact"
+    )]
+    pub envelope: Option<EnvelopeFormat>,
+    #[arg(long, value_parser = EcdsaSigFormat::from_str, default_value = "der", help = "ECDSA signature format: der or raw (r || s), verify accepts both"
+    )]
+    pub ecdsa_sig: EcdsaSigFormat,
+    #[arg(long, help = CONTEXT_HELP)]
+    pub context: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "envelope",
+        help = "Signed comment of a minisign signature [default: timestamp and file name]"
+    )]
+    pub trusted_comment: Option<String>,
 }
 
 #[derive(Debug, Parser)]
@@ -34,24 +94,186 @@ pub struct TextVerifyOpts {
     pub input: String,
     #[arg(short, long, value_parser = check_input, default_value = "-")]
     pub key: String,
-    #[arg(long, value_parser = TextSignFormat::from_str, default_value = "blake3")]
-    pub format: TextSignFormat,
-    #[arg(short, long)]
-    pub sig: String,
+    #[arg(
+        long,
+        conflicts_with = "key",
+        help = "Verify with a keyring key, by name or key id"
+    )]
+    pub key_id: Option<String>,
+    #[arg(long, value_parser = TextSignFormat::from_str, help = "Signature format [default: blake3, or the algorithm of a signature envelope or --key-id key]"
+    )]
+    pub format: Option<TextSignFormat>,
+    #[arg(
+        short,
+        long,
+        required_unless_present = "sig_file",
+        conflicts_with = "sig_file"
+    )]
+    pub sig: Option<String>,
+    #[arg(long, value_parser = check_input, help = "Read the signature from a detached signature file")]
+    pub sig_file: Option<String>,
+    #[arg(long, value_parser = SigEncoding::from_str, help = "Signature encoding: hex, base64, base64url or raw [default: hex for hmac formats, base64url otherwise]"
+    )]
+    pub sig_encoding: Option<SigEncoding>,
+    #[arg(long, help = CONTEXT_HELP)]
+    pub context: Option<String>,
+    #[arg(long, help = "Print the result as a JSON object")]
+    pub json: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextSignDirOpts {
+    #[arg(short, long, value_parser = check_path, help = "Directory to sign")]
+    pub dir: PathBuf,
+    #[arg(short, long, value_parser = check_input, help = "Ed25519 secret key file")]
+    pub key: String,
+    #[arg(
+        short,
+        long,
+        default_value = "manifest.json",
+        help = "File to write the signed manifest to"
+    )]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextVerifyDirOpts {
+    #[arg(short, long, value_parser = check_path, help = "Directory to verify")]
+    pub dir: PathBuf,
+    #[arg(short, long, value_parser = check_input, help = "Ed25519 public key file")]
+    pub key: String,
+    #[arg(short, long, value_parser = check_input, default_value = "manifest.json")]
+    pub manifest: String,
+    #[arg(short, long, help = "Also list unchanged files")]
+    pub verbose: bool,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum TextSignFormat {
     Blake3,
     Ed25519,
+    Ed25519ph,
+    HmacSha256,
+    HmacSha512,
+    EcdsaP256,
+    EcdsaSecp256k1,
+    Minisign,
+}
+
+/// Type of a generated or keyring key: a signing key, or an x25519 key for seal/open
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyFormat {
+    Sign(TextSignFormat),
+    X25519,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnvelopeFormat {
+    Json,
+    Compact,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EcdsaSigFormat {
+    Der,
+    Raw,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextEncryptOpts {
+    #[arg(short, long, value_parser = check_input, default_value = "-")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "Output file to write the ciphertext to"
+    )]
+    pub output: String,
+    #[arg(short, long, value_parser = check_input, help = "Key file generated with --format blake3")]
+    pub key: String,
+    #[arg(long, value_parser = TextCipher::from_str, default_value = "xchacha20-poly1305")]
+    pub cipher: TextCipher,
+    #[arg(long, help = CONTEXT_HELP)]
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextDecryptOpts {
+    #[arg(short, long, value_parser = check_input, default_value = "-")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "Output file to write the plaintext to"
+    )]
+    pub output: String,
+    #[arg(short, long, value_parser = check_input, help = "Key file generated with --format blake3")]
+    pub key: String,
+    #[arg(long, help = CONTEXT_HELP)]
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextSealOpts {
+    #[arg(short, long, value_parser = check_input, default_value = "-")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "Output file to write the sealed message to"
+    )]
+    pub output: String,
+    #[arg(short, long = "recipient", value_parser = check_input, required = true, help = "Public key file generated with --format x25519, can be repeated")]
+    pub recipients: Vec<String>,
+    #[arg(short, long, help = "Write a base64 armored message instead of binary")]
+    pub armor: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextOpenOpts {
+    #[arg(short, long, value_parser = check_input, default_value = "-")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "Output file to write the plaintext to"
+    )]
+    pub output: String,
+    #[arg(long, value_parser = check_input, help = "Secret key file generated with --format x25519")]
+    pub identity: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextCipher {
+    XChaCha20Poly1305,
+    ChaCha20Poly1305,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SigEncoding {
+    Hex,
+    Base64,
+    Base64Url,
+    Raw,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextGenerateKeyOpts {
-    #[arg(long, value_parser = TextSignFormat::from_str, default_value = "blake3")]
-    pub format: TextSignFormat,
+    #[arg(long, value_parser = KeyFormat::from_str, default_value = "blake3", help = "Any signature format, or x25519 for seal/open")]
+    pub format: KeyFormat,
     #[arg(short, long, value_parser = check_path)]
     pub output: PathBuf,
+    #[arg(
+        long,
+        help = "Protect the secret key with a passphrase, read from RCLI_KEY_PASSPHRASE or prompted"
+    )]
+    pub encrypt: bool,
+    #[arg(long, help = "Write a blake3 or hmac key hex encoded")]
+    pub hex: bool,
 }
 
 fn check_path(s: &str) -> Result<PathBuf, &'static str> {
@@ -63,6 +285,25 @@ fn check_path(s: &str) -> Result<PathBuf, &'static str> {
     }
 }
 
+impl TextSignFormat {
+    pub fn is_symmetric(self) -> bool {
+        matches!(
+            self,
+            TextSignFormat::Blake3 | TextSignFormat::HmacSha256 | TextSignFormat::HmacSha512
+        )
+    }
+
+    /// Signature encoding used when none is given, HMAC signatures are usually exchanged as hex
+    /// and minisign signatures are already text files
+    pub fn default_sig_encoding(self) -> SigEncoding {
+        match self {
+            TextSignFormat::HmacSha256 | TextSignFormat::HmacSha512 => SigEncoding::Hex,
+            TextSignFormat::Minisign => SigEncoding::Raw,
+            _ => SigEncoding::Base64Url,
+        }
+    }
+}
+
 impl FromStr for TextSignFormat {
     type Err = &'static str;
 
@@ -70,6 +311,12 @@ impl FromStr for TextSignFormat {
         match s {
             "blake3" => Ok(Self::Blake3),
             "ed25519" => Ok(Self::Ed25519),
+            "ed25519ph" => Ok(Self::Ed25519ph),
+            "hmac-sha256" => Ok(Self::HmacSha256),
+            "hmac-sha512" => Ok(Self::HmacSha512),
+            "ecdsa-p256" => Ok(Self::EcdsaP256),
+            "ecdsa-secp256k1" => Ok(Self::EcdsaSecp256k1),
+            "minisign" => Ok(Self::Minisign),
             _ => Err("Invalid text sign format"),
         }
     }
@@ -80,6 +327,12 @@ impl From<TextSignFormat> for &'static str {
         match f {
             TextSignFormat::Blake3 => "blake3",
             TextSignFormat::Ed25519 => "ed25519",
+            TextSignFormat::Ed25519ph => "ed25519ph",
+            TextSignFormat::HmacSha256 => "hmac-sha256",
+            TextSignFormat::HmacSha512 => "hmac-sha512",
+            TextSignFormat::EcdsaP256 => "ecdsa-p256",
+            TextSignFormat::EcdsaSecp256k1 => "ecdsa-secp256k1",
+            TextSignFormat::Minisign => "minisign",
         }
     }
 }
@@ -93,46 +346,516 @@ impl fmt::Display for TextSignFormat {
             TextSignFormat::Ed25519 => {
                 write!(f, "ed25519")
             }
+            TextSignFormat::Ed25519ph => {
+                write!(f, "ed25519ph")
+            }
+            TextSignFormat::HmacSha256 => {
+                write!(f, "hmac-sha256")
+            }
+            TextSignFormat::HmacSha512 => {
+                write!(f, "hmac-sha512")
+            }
+            TextSignFormat::EcdsaP256 => {
+                write!(f, "ecdsa-p256")
+            }
+            TextSignFormat::EcdsaSecp256k1 => {
+                write!(f, "ecdsa-secp256k1")
+            }
+            TextSignFormat::Minisign => {
+                write!(f, "minisign")
+            }
+        }
+    }
+}
+
+impl KeyFormat {
+    pub fn is_symmetric(self) -> bool {
+        matches!(self, KeyFormat::Sign(format) if format.is_symmetric())
+    }
+
+    /// The signature format of the key, x25519 keys cannot sign
+    pub fn sign_format(self) -> anyhow::Result<TextSignFormat> {
+        match self {
+            KeyFormat::Sign(format) => Ok(format),
+            KeyFormat::X25519 => Err(anyhow::anyhow!(
+                "x25519 keys are for text seal/open, they cannot sign"
+            )),
         }
     }
 }
 
+impl FromStr for KeyFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x25519" => Ok(Self::X25519),
+            _ => TextSignFormat::from_str(s)
+                .map(Self::Sign)
+                .map_err(|_| "Invalid key format"),
+        }
+    }
+}
+
+impl From<KeyFormat> for &'static str {
+    fn from(f: KeyFormat) -> Self {
+        match f {
+            KeyFormat::Sign(format) => format.into(),
+            KeyFormat::X25519 => "x25519",
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+impl FromStr for EnvelopeFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "compact" => Ok(Self::Compact),
+            _ => Err("Invalid envelope format"),
+        }
+    }
+}
+
+impl From<EnvelopeFormat> for &'static str {
+    fn from(f: EnvelopeFormat) -> Self {
+        match f {
+            EnvelopeFormat::Json => "json",
+            EnvelopeFormat::Compact => "compact",
+        }
+    }
+}
+
+impl fmt::Display for EnvelopeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+impl FromStr for EcdsaSigFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "der" => Ok(Self::Der),
+            "raw" => Ok(Self::Raw),
+            _ => Err("Invalid ECDSA signature format"),
+        }
+    }
+}
+
+impl From<EcdsaSigFormat> for &'static str {
+    fn from(f: EcdsaSigFormat) -> Self {
+        match f {
+            EcdsaSigFormat::Der => "der",
+            EcdsaSigFormat::Raw => "raw",
+        }
+    }
+}
+
+impl fmt::Display for EcdsaSigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+impl FromStr for TextCipher {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "xchacha20-poly1305" => Ok(Self::XChaCha20Poly1305),
+            "chacha20-poly1305" => Ok(Self::ChaCha20Poly1305),
+            _ => Err("Invalid cipher"),
+        }
+    }
+}
+
+impl From<TextCipher> for &'static str {
+    fn from(c: TextCipher) -> Self {
+        match c {
+            TextCipher::XChaCha20Poly1305 => "xchacha20-poly1305",
+            TextCipher::ChaCha20Poly1305 => "chacha20-poly1305",
+        }
+    }
+}
+
+impl fmt::Display for TextCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+impl FromStr for SigEncoding {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            "base64url" => Ok(Self::Base64Url),
+            "raw" => Ok(Self::Raw),
+            _ => Err("Invalid signature encoding"),
+        }
+    }
+}
+
+impl From<SigEncoding> for &'static str {
+    fn from(e: SigEncoding) -> Self {
+        match e {
+            SigEncoding::Hex => "hex",
+            SigEncoding::Base64 => "base64",
+            SigEncoding::Base64Url => "base64url",
+            SigEncoding::Raw => "raw",
+        }
+    }
+}
+
+impl fmt::Display for SigEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
 impl CmdExecutor for TextSignOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let signed = process_sign(&self.input, &self.key, self.format)?;
-        println!("Signature: {}", signed);
+        let (key, format) = resolve_key(&self.key, self.key_id.as_deref(), self.format, true)?;
+        if let Some(envelope) = self.envelope {
+            let envelope =
+                process_sign_envelope(&self.input, &key, format, self.context.as_deref())?
+                    .encode(envelope)?;
+            match self.sig_out {
+                Some(path) => {
+                    tokio::fs::write(&path, format!("{}\n", envelope)).await?;
+                    println!("Signature written to {:?}", path);
+                }
+                None => println!("{}", envelope),
+            }
+            return Ok(());
+        }
+
+        let signed = match format {
+            TextSignFormat::Minisign => {
+                if self.sig_encoding.is_some_and(|e| e != SigEncoding::Raw) {
+                    return Err(anyhow::anyhow!(
+                        "minisign signatures are text files, only --sig-encoding raw is supported"
+                    ));
+                }
+                process_sign_minisign(&self.input, &key, self.trusted_comment.as_deref())?
+            }
+            _ if self.trusted_comment.is_some() => {
+                return Err(anyhow::anyhow!(
+                    "--trusted-comment is only supported with --format minisign"
+                ));
+            }
+            _ => process_sign(&self.input, &key, format, self.context.as_deref())?,
+        };
+        let signed = convert_ecdsa_signature(&signed, format, self.ecdsa_sig)?;
+        let encoding = self
+            .sig_encoding
+            .unwrap_or_else(|| format.default_sig_encoding());
+        let mut encoded = encode_signature(&signed, encoding);
+        match self.sig_out {
+            Some(path) => {
+                if encoding != SigEncoding::Raw {
+                    encoded.push(b'\n');
+                }
+                tokio::fs::write(&path, encoded).await?;
+                println!("Signature written to {:?}", path);
+            }
+            None if encoding == SigEncoding::Raw => {
+                std::io::stdout().write_all(&encoded)?;
+            }
+            None => {
+                println!("Signature: {}", String::from_utf8(encoded)?);
+            }
+        }
         Ok(())
     }
 }
 
 impl CmdExecutor for TextVerifyOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let verified = process_verify(&self.input, &self.key, &self.sig, self.format)?;
-        match verified {
-            true => {
-                println!("Signature verified");
+        // every error carries its cause, so the exit code matches the one of the JSON report
+        let result = self
+            .verify()
+            .await
+            .with_failure(VerifyFailure::MalformedInput);
+        if self.json {
+            let report = match &result {
+                Ok(trusted_comment) => VerifyReport {
+                    trusted_comment: trusted_comment.clone(),
+                    ..VerifyReport::verified(None)
+                },
+                Err(e) => VerifyReport::failed(e),
+            };
+            println!("{}", serde_json::to_string(&report)?);
+        } else if let Ok(trusted_comment) = &result {
+            println!("Signature verified");
+            if let Some(comment) = trusted_comment {
+                println!("Trusted comment: {}", comment);
             }
-            false => {
-                eprintln!("Signature not verified");
+        }
+        result.map(|_| ())
+    }
+}
+
+impl TextVerifyOpts {
+    /// Verify the signature, returning the trusted comment of minisign signatures
+    async fn verify(&self) -> anyhow::Result<Option<String>> {
+        let (key, key_format) = resolve_key(&self.key, self.key_id.as_deref(), self.format, false)
+            .with_failure(VerifyFailure::KeyError)?;
+        // an explicit format, or the one of the keyring key
+        let expected_format = self.format.or(self.key_id.is_some().then_some(key_format));
+        let sig = match (&self.sig, &self.sig_file) {
+            (Some(sig), _) => sig.as_bytes().to_vec(),
+            (None, Some(path)) => tokio::fs::read(path)
+                .await
+                .with_failure(VerifyFailure::MalformedInput)?,
+            (None, None) => unreachable!("clap requires --sig or --sig-file"),
+        };
+        // Envelopes carry their own algorithm, an explicit encoding means a bare signature
+        if self.sig_encoding.is_none() {
+            if let Some(envelope) = SignatureEnvelope::detect(&sig) {
+                let envelope = envelope.with_failure(VerifyFailure::MalformedInput)?;
+                let format = envelope
+                    .format()
+                    .with_failure(VerifyFailure::MalformedInput)?;
+                if let Some(expected) = expected_format.filter(|f| *f != format) {
+                    return Err(VerifyError::new(
+                        VerifyFailure::KeyError,
+                        format!(
+                            "Expected a {} signature, the envelope is {}",
+                            expected, format
+                        ),
+                    )
+                    .into());
+                }
+                let verified =
+                    process_verify_envelope(&self.input, &key, &envelope, self.context.as_deref())?;
+                verified_or_invalid(verified)?;
+                return Ok(None);
             }
         }
-        Ok(())
+
+        let format = key_format;
+        let encoding = self
+            .sig_encoding
+            .unwrap_or_else(|| format.default_sig_encoding());
+        let sig = decode_signature(&sig, encoding).with_failure(VerifyFailure::MalformedInput)?;
+        let verified = process_verify(&self.input, &key, &sig, format, self.context.as_deref())?;
+        verified_or_invalid(verified)?;
+        match format {
+            TextSignFormat::Minisign => Ok(Some(MinisignSignature::parse(&sig)?.trusted_comment)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Key file and format to use, `key_id` looks the key up in the keyring instead of using `key`
+fn resolve_key(
+    key: &str,
+    key_id: Option<&str>,
+    format: Option<TextSignFormat>,
+    secret: bool,
+) -> anyhow::Result<(String, TextSignFormat)> {
+    match key_id {
+        Some(key_id) => Keyring::open_default()?.resolve(key_id, format, secret),
+        None => Ok((key.to_string(), format.unwrap_or(TextSignFormat::Blake3))),
+    }
+}
+
+fn verified_or_invalid(verified: bool) -> anyhow::Result<()> {
+    match verified {
+        true => Ok(()),
+        false => Err(VerifyError::new(VerifyFailure::Invalid, "Signature not verified").into()),
     }
 }
 
 impl CmdExecutor for TextGenerateKeyOpts {
     async fn execute(self) -> anyhow::Result<()> {
+        if self.hex && !self.format.is_symmetric() {
+            return Err(anyhow::anyhow!(
+                "--hex is only supported for symmetric keys"
+            ));
+        }
+        let passphrase = match self.encrypt {
+            true => Some(read_new_passphrase()?),
+            false => None,
+        };
+        if self.format == KeyFormat::Sign(TextSignFormat::Minisign) {
+            // minisign encrypts its secret keys itself, other tools could not read an rcli wrapper
+            let Key::Asymmetric { public, secret } = generate_minisign_key(passphrase.as_deref())?
+            else {
+                unreachable!("minisign keys are asymmetric")
+            };
+            tokio::fs::write(self.output.join("minisign.pub"), public).await?;
+            write_secret_key_file(self.output.join("minisign.key"), &secret)?;
+            println!("Key generated to {:?}", self.output);
+            return Ok(());
+        }
         let key = generate_key(self.format)?;
+        let protect = |secret: Vec<u8>| match &passphrase {
+            Some(passphrase) => encrypt_key(&secret, passphrase.as_bytes()),
+            None => Ok(secret),
+        };
         match key {
-            Key::Symmetric { key } => {
-                tokio::fs::write(self.output.join("blake3.key"), key).await?;
+            Key::Symmetric { mut key } => {
+                if self.hex {
+                    key = encode_bytes(&key, CodecFormat::Hex);
+                    key.push(b'\n');
+                }
+                let name = match self.format {
+                    KeyFormat::Sign(TextSignFormat::Blake3) => "blake3.key",
+                    _ => "hmac.key",
+                };
+                write_secret_key_file(self.output.join(name), &protect(key)?)?;
             }
             Key::Asymmetric { public, secret } => {
                 tokio::fs::write(self.output.join("pk.pem"), public).await?;
-                tokio::fs::write(self.output.join("sk.pem"), secret).await?;
+                write_secret_key_file(self.output.join("sk.pem"), &protect(secret)?)?;
             }
         }
         println!("Key generated to {:?}", self.output);
         Ok(())
     }
 }
+
+impl CmdExecutor for TextEncryptOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = Blake3::from_file_with_context(&self.key, self.context.as_deref())?;
+        let reader = get_reader(&self.input)?;
+        let writer = get_writer(&self.output)?;
+        process_encrypt(reader, writer, &key, self.cipher)?;
+        Ok(())
+    }
+}
+
+impl CmdExecutor for TextDecryptOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = Blake3::from_file_with_context(&self.key, self.context.as_deref())?;
+        let reader = get_reader(&self.input)?;
+        let writer = get_writer(&self.output)?;
+        if let Err(e) = process_decrypt(reader, writer, &key) {
+            // Do not leave partially decrypted data behind
+            if self.output != "-" {
+                let _ = std::fs::remove_file(&self.output);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for TextSealOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|path| decode_x25519_public(&std::fs::read(path)?))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let reader = get_reader(&self.input)?;
+        let writer = get_writer(&self.output)?;
+        process_seal(reader, writer, &recipients, self.armor)?;
+        Ok(())
+    }
+}
+
+impl CmdExecutor for TextOpenOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let identity = decode_x25519_secret(&read_secret_key_file(&self.identity)?)?;
+        let reader = get_reader(&self.input)?;
+        let writer = get_writer(&self.output)?;
+        if let Err(e) = process_open(reader, writer, &identity) {
+            // Do not leave partially decrypted data behind
+            if self.output != "-" {
+                let _ = std::fs::remove_file(&self.output);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for TextSignDirOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let signed = process_sign_dir(&self.dir, &self.key, Some(&self.output))?;
+        let json = serde_json::to_string_pretty(&signed)?;
+        tokio::fs::write(&self.output, json + "\n").await?;
+        println!(
+            "Manifest of {} files written to {:?}",
+            signed.manifest.files.len(),
+            self.output
+        );
+        Ok(())
+    }
+}
+
+impl CmdExecutor for TextVerifyDirOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let signed: SignedManifest =
+            serde_json::from_slice(&tokio::fs::read(&self.manifest).await?)?;
+        let exclude = PathBuf::from(&self.manifest);
+        let diff = process_verify_dir(&self.dir, &self.key, &signed, Some(&exclude))?;
+        println!("Manifest signature verified");
+        let report = [
+            ("added", &diff.added),
+            ("removed", &diff.removed),
+            ("modified", &diff.modified),
+        ];
+        for (status, paths) in report {
+            for path in paths {
+                println!("{:<9} {}", status, path);
+            }
+        }
+        if self.verbose {
+            for path in &diff.unchanged {
+                println!("{:<9} {}", "unchanged", path);
+            }
+        }
+        println!(
+            "{} added, {} removed, {} modified, {} unchanged",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.modified.len(),
+            diff.unchanged.len()
+        );
+        match diff.is_clean() {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!("Directory does not match the manifest")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_unreadable_input_exit_code() {
+        let opts = TextVerifyOpts::try_parse_from([
+            "verify",
+            "--input",
+            "src",
+            "--key",
+            "fixtures/public.key",
+            "--format",
+            "ed25519",
+            "--sig",
+            "abc",
+        ])
+        .unwrap();
+        let err = opts.execute().await.unwrap_err();
+        let report = VerifyReport::failed(&err);
+        let err = err.downcast_ref::<VerifyError>().unwrap();
+        assert_eq!(err.exit_code(), VerifyFailure::MalformedInput.exit_code());
+        assert_eq!(err.exit_code(), report.exit_code);
+    }
+}
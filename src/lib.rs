@@ -5,10 +5,13 @@ pub mod process;
 pub mod utils;
 
 pub use crate::base64::*;
+pub use crate::codec::*;
 pub use crate::csv::*;
 pub use crate::gen_pass::*;
+pub use crate::hash::*;
 pub use crate::http::*;
 pub use crate::jwt::*;
+pub use crate::key::*;
 pub use crate::text::*;
 pub use cli::*;
 
@@ -1,11 +1,19 @@
 use clap::Parser;
 use rcli::cli::Opts;
+use rcli::process::verify::VerifyError;
 use rcli::CmdExecutor;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
     let opts = Opts::parse();
-    opts.command.execute().await?;
+    if let Err(e) = opts.command.execute().await {
+        // verify commands exit with a code telling why the verification failed
+        if let Some(err) = e.downcast_ref::<VerifyError>() {
+            eprintln!("Error: {}", err);
+            std::process::exit(err.exit_code());
+        }
+        return Err(e);
+    }
     Ok(())
 }
@@ -1,50 +1,707 @@
 use crate::cli::base64::Base64Format;
-use crate::utils::get_reader;
-use base64::prelude::BASE64_URL_SAFE_NO_PAD;
-use base64::{engine::general_purpose::STANDARD, Engine};
-use std::io::Read;
+use base64::alphabet::{self, Alphabet};
+use base64::engine::general_purpose::{
+    GeneralPurposeConfig, STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD,
+};
+use base64::engine::{DecodePaddingMode, GeneralPurpose};
+use base64::read::DecoderReader;
+use base64::write::EncoderWriter;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 
-pub fn process_encode(input: &str, format: Base64Format) -> anyhow::Result<String> {
-    let mut reader = get_reader(input)?;
+const CHUNK_SIZE: usize = 64 * 1024;
+const MIME_LINE_WIDTH: usize = 76;
+const DATA_URI_DEFAULT_MIME: &str = "text/plain;charset=US-ASCII";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DataUri {
+    pub mime: String,
+    pub payload: Vec<u8>,
+}
+
+/// Stream `reader` through the base64 encoder into `writer`, returns the number of input bytes
+///
+/// The output is broken into lines of `wrap` characters if given, MIME output is always
+/// wrapped (at 76 characters unless told otherwise) with CRLF line endings.
+pub fn process_encode(
+    reader: impl Read,
+    writer: impl Write,
+    format: Base64Format,
+    wrap: Option<usize>,
+) -> anyhow::Result<u64> {
+    let mut reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+    let (width, separator): (_, &[u8]) = match format {
+        Base64Format::Mime => (wrap.or(Some(MIME_LINE_WIDTH)), b"\r\n"),
+        _ => (wrap, b"\n"),
+    };
+    let writer = LineWrapWriter::new(writer, width.unwrap_or(0), separator);
+    let mut encoder = EncoderWriter::new(writer, encode_engine(format));
+    let n = io::copy(&mut reader, &mut encoder)?;
+    let mut writer = encoder.finish()?;
+    writer.flush()?;
+    Ok(n)
+}
+
+/// Stream base64 text from `reader` into raw bytes in `writer`, returns the number of decoded bytes
+///
+/// Leading and trailing whitespace of the input is ignored. In `lenient` mode (and for MIME input)
+/// whitespace and line breaks anywhere in the input are skipped and padding is optional.
+pub fn process_decode(
+    reader: impl Read,
+    mut writer: impl Write,
+    format: Base64Format,
+    lenient: bool,
+) -> anyhow::Result<u64> {
+    let lenient = lenient || matches!(format, Base64Format::Mime);
+    let reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+    let reader = if lenient {
+        WhitespaceReader::strip(reader)
+    } else {
+        WhitespaceReader::trim(reader)
+    };
+    let engine = decode_engine(format, lenient);
+    let mut decoder = DecoderReader::new(reader, &engine);
+    let n = io::copy(&mut decoder, &mut writer)?;
+    writer.flush()?;
+    Ok(n)
+}
+
+/// Decode base64 text of unknown variant, returns the detected format and the number of decoded bytes
+///
+/// The alphabet is chosen from the first `-_` or `+/` symbol of the input, whitespace and line
+/// breaks are skipped and padding is optional. Invalid input is reported with its line and column.
+pub fn process_decode_auto(
+    reader: impl Read,
+    mut writer: impl Write,
+) -> anyhow::Result<(Base64Format, u64)> {
+    let mut reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+    let mut prefix = Vec::new();
+    let url_safe = loop {
+        let start = prefix.len();
+        let n = reader
+            .by_ref()
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut prefix)?;
+        if let Some(url_safe) = detect_url_safe(&prefix[start..]) {
+            break url_safe;
+        }
+        if n == 0 {
+            break false;
+        }
+    };
+
+    let reader = AutoReader::new(io::Cursor::new(prefix).chain(reader), url_safe);
+    let format = if url_safe {
+        Base64Format::UrlSafe
+    } else {
+        Base64Format::Standard
+    };
+    let engine = decode_engine(format, true);
+    let mut decoder = DecoderReader::new(reader, &engine);
+    let n = io::copy(&mut decoder, &mut writer)?;
+    writer.flush()?;
+
+    Ok((decoder.into_inner().format(), n))
+}
+
+/// Look for the first symbol that only exists in one of the two base64 alphabets
+fn detect_url_safe(sample: &[u8]) -> Option<bool> {
+    sample.iter().find_map(|b| match b {
+        b'-' | b'_' => Some(true),
+        b'+' | b'/' => Some(false),
+        _ => None,
+    })
+}
+
+/// Encode the input as a `data:<mime>;base64,...` URI, returns the sniffed MIME type and the number of input bytes
+///
+/// The MIME type is sniffed from the magic bytes of the input, falling back to the extension of `name`.
+pub fn process_encode_data_uri(
+    reader: impl Read,
+    mut writer: impl Write,
+    name: &str,
+) -> anyhow::Result<(String, u64)> {
+    let mut reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+    let mime = sniff_mime(name, reader.fill_buf()?);
+    write!(writer, "data:{};base64,", mime)?;
+    let n = process_encode(reader, writer, Base64Format::Standard, None)?;
+    Ok((mime, n))
+}
+
+/// Parse a data URI and decode its payload
+///
+/// Data URIs are meant for small assets, so the payload is decoded in memory.
+pub fn process_decode_data_uri(mut reader: impl Read) -> anyhow::Result<DataUri> {
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
+    let uri = buf.trim_ascii();
+
+    if uri.len() < 5 || !uri[..5].eq_ignore_ascii_case(b"data:") {
+        return Err(anyhow::anyhow!("Not a data URI, missing \"data:\" scheme"));
+    }
+    let comma = uri
+        .iter()
+        .position(|&b| b == b',')
+        .ok_or_else(|| anyhow::anyhow!("Invalid data URI, missing ','"))?;
+    let header = std::str::from_utf8(&uri[5..comma])?;
+    let payload = percent_decode(&uri[comma + 1..])?;
+
+    let (mime, base64) = match header.rsplit_once(';') {
+        Some((mime, param)) if param.trim().eq_ignore_ascii_case("base64") => (mime, true),
+        _ => (header, false),
+    };
+    let mime = match mime.trim() {
+        "" => DATA_URI_DEFAULT_MIME.to_string(),
+        mime if mime.starts_with(';') => format!("text/plain{}", mime),
+        mime => mime.to_string(),
+    };
 
-    let content = match format {
-        Base64Format::UrlSafe => BASE64_URL_SAFE_NO_PAD.encode(&buf),
-        Base64Format::Standard => STANDARD.encode(&buf),
+    let payload = if base64 {
+        let mut decoded = Vec::new();
+        process_decode(
+            payload.as_slice(),
+            &mut decoded,
+            Base64Format::Standard,
+            true,
+        )?;
+        decoded
+    } else {
+        payload
     };
 
-    Ok(content)
+    Ok(DataUri { mime, payload })
 }
 
-pub fn process_decode(input: &str, format: Base64Format) -> anyhow::Result<Vec<u8>> {
-    let mut reader = get_reader(input)?;
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf)?;
+/// Pick a file extension for a data URI payload, by its content first and then by its MIME type
+pub fn data_uri_extension(uri: &DataUri) -> &'static str {
+    if let Some(t) = infer::get(&uri.payload) {
+        return t.extension();
+    }
+    let mime = uri.mime.split(';').next().unwrap_or_default().trim();
+    match mime {
+        "text/plain" => "txt",
+        "text/javascript" | "application/javascript" => "js",
+        "text/html" => "html",
+        "image/jpeg" => "jpg",
+        "application/octet-stream" => "bin",
+        _ => {
+            let subtype = mime.rsplit('/').next().unwrap_or_default();
+            mime_guess::get_mime_extensions_str(mime)
+                .and_then(|exts| exts.iter().find(|e| **e == subtype).or(exts.first()))
+                .copied()
+                .unwrap_or("bin")
+        }
+    }
+}
 
-    let decoded = match format {
-        Base64Format::UrlSafe => BASE64_URL_SAFE_NO_PAD.decode(buf.trim())?,
-        Base64Format::Standard => STANDARD.decode(buf.trim())?,
-    };
+/// File to write a decoded data URI payload to when no output is given: the input file name with
+/// the payload extension, or `decoded.<ext>` for stdin. The input file itself is never picked.
+pub fn data_uri_output_path(input: &str, uri: &DataUri) -> PathBuf {
+    let ext = data_uri_extension(uri);
+    let input = Path::new(input);
+    match input.file_stem() {
+        Some(stem) if input != Path::new("-") => {
+            let path = input.with_extension(ext);
+            match path == input {
+                true => input.with_file_name(format!("{}.decoded.{}", stem.to_string_lossy(), ext)),
+                false => path,
+            }
+        }
+        _ => PathBuf::from(format!("decoded.{}", ext)),
+    }
+}
 
-    Ok(decoded)
+fn sniff_mime(name: &str, head: &[u8]) -> String {
+    if let Some(t) = infer::get(head) {
+        return t.mime_type().to_string();
+    }
+    if let Some(mime) = mime_guess::from_path(name).first() {
+        return mime.to_string();
+    }
+    // A multi-byte character cut off at the end of the buffer is still text
+    match std::str::from_utf8(head) {
+        Err(e) if e.error_len().is_some() => "application/octet-stream".to_string(),
+        _ => "text/plain".to_string(),
+    }
+}
+
+fn percent_decode(input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'%' {
+            let hex = input
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid percent escape at offset {}", i))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(input[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+fn encode_engine(format: Base64Format) -> &'static GeneralPurpose {
+    match format {
+        Base64Format::Standard | Base64Format::Mime => &STANDARD,
+        Base64Format::StandardNoPad => &STANDARD_NO_PAD,
+        Base64Format::UrlSafe => &URL_SAFE_NO_PAD,
+        Base64Format::UrlSafePad => &URL_SAFE,
+    }
+}
+
+fn decode_engine(format: Base64Format, lenient: bool) -> GeneralPurpose {
+    if !lenient {
+        return encode_engine(format).clone();
+    }
+    let config =
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    GeneralPurpose::new(alphabet(format), config)
+}
+
+fn alphabet(format: Base64Format) -> &'static Alphabet {
+    match format {
+        Base64Format::Standard | Base64Format::StandardNoPad | Base64Format::Mime => {
+            &alphabet::STANDARD
+        }
+        Base64Format::UrlSafe | Base64Format::UrlSafePad => &alphabet::URL_SAFE,
+    }
+}
+
+/// Writer adapter that inserts `separator` after every `width` bytes, a `width` of 0 disables wrapping
+pub(crate) struct LineWrapWriter<W> {
+    inner: W,
+    width: usize,
+    separator: &'static [u8],
+    column: usize,
+}
+
+impl<W: Write> LineWrapWriter<W> {
+    pub(crate) fn new(inner: W, width: usize, separator: &'static [u8]) -> Self {
+        Self {
+            inner,
+            width,
+            separator,
+            column: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for LineWrapWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.width == 0 {
+            return self.inner.write(buf);
+        }
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.column == self.width {
+                self.inner.write_all(self.separator)?;
+                self.column = 0;
+            }
+            let n = rest.len().min(self.width - self.column);
+            self.inner.write_all(&rest[..n])?;
+            self.column += n;
+            rest = &rest[n..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader adapter that drops whitespace of a stream.
+///
+/// In trim mode whitespace inside the stream is held back until the next non-whitespace byte
+/// shows up, so it is only passed through when it is not at the end. In strip mode all
+/// whitespace is dropped.
+struct WhitespaceReader<R> {
+    inner: R,
+    strip: bool,
+    started: bool,
+    pending: Vec<u8>,
+    queue: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> WhitespaceReader<R> {
+    fn trim(inner: R) -> Self {
+        Self::new(inner, false)
+    }
+
+    fn strip(inner: R) -> Self {
+        Self::new(inner, true)
+    }
+
+    fn new(inner: R, strip: bool) -> Self {
+        Self {
+            inner,
+            strip,
+            started: false,
+            pending: Vec::new(),
+            queue: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for WhitespaceReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; 4096];
+        while self.pos == self.queue.len() {
+            let n = self.inner.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            self.queue.clear();
+            self.pos = 0;
+            for &b in &chunk[..n] {
+                if b.is_ascii_whitespace() {
+                    if self.started && !self.strip {
+                        self.pending.push(b);
+                    }
+                } else {
+                    self.started = true;
+                    self.queue.append(&mut self.pending);
+                    self.queue.push(b);
+                }
+            }
+        }
+        let n = buf.len().min(self.queue.len() - self.pos);
+        buf[..n].copy_from_slice(&self.queue[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Reader adapter used by the auto detecting decoder.
+///
+/// It validates every byte against the detected alphabet while keeping track of its position,
+/// drops whitespace and records the shape of the input (padding, line width) to name the variant.
+struct AutoReader<R> {
+    inner: R,
+    url_safe: bool,
+    offset: u64,
+    line: u64,
+    column: u64,
+    symbols: u64,
+    padding: u64,
+    crlf: bool,
+    first_line: Option<u64>,
+}
+
+impl<R: Read> AutoReader<R> {
+    fn new(inner: R, url_safe: bool) -> Self {
+        Self {
+            inner,
+            url_safe,
+            offset: 0,
+            line: 1,
+            column: 0,
+            symbols: 0,
+            padding: 0,
+            crlf: false,
+            first_line: None,
+        }
+    }
+
+    fn format(&self) -> Base64Format {
+        let padded = self.padding > 0 || self.symbols.is_multiple_of(4);
+        match (self.url_safe, padded) {
+            (true, true) => Base64Format::UrlSafePad,
+            (true, false) => Base64Format::UrlSafe,
+            (false, _) if self.crlf && self.first_line == Some(MIME_LINE_WIDTH as u64) => {
+                Base64Format::Mime
+            }
+            (false, true) => Base64Format::Standard,
+            (false, false) => Base64Format::StandardNoPad,
+        }
+    }
+
+    fn is_symbol(&self, b: u8) -> bool {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => true,
+            b'-' | b'_' => self.url_safe,
+            b'+' | b'/' => !self.url_safe,
+            _ => false,
+        }
+    }
+
+    fn error(&self, msg: impl fmt::Display) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} at line {}, column {} (offset {})",
+                msg, self.line, self.column, self.offset
+            ),
+        )
+    }
+
+    fn check_end(&self) -> io::Result<()> {
+        if self.symbols % 4 == 1 {
+            return Err(self.error(format!(
+                "Truncated input, {} base64 symbols can not be decoded",
+                self.symbols
+            )));
+        }
+        if self.padding > 0 && !(self.symbols + self.padding).is_multiple_of(4) {
+            return Err(self.error("Invalid padding"));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for AutoReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; 4096];
+        loop {
+            let len = buf.len().min(chunk.len());
+            let n = self.inner.read(&mut chunk[..len])?;
+            if n == 0 {
+                self.check_end()?;
+                return Ok(0);
+            }
+
+            let mut written = 0;
+            for &b in &chunk[..n] {
+                self.offset += 1;
+                self.column += 1;
+                match b {
+                    b'\n' => {
+                        if self.first_line.is_none() {
+                            self.first_line = Some(self.column - 1 - self.crlf as u64);
+                        }
+                        self.line += 1;
+                        self.column = 0;
+                        continue;
+                    }
+                    b'\r' => {
+                        self.crlf = true;
+                        continue;
+                    }
+                    b if b.is_ascii_whitespace() => continue,
+                    b'=' => self.padding += 1,
+                    b if self.is_symbol(b) && self.padding == 0 => self.symbols += 1,
+                    b if self.is_symbol(b) => {
+                        return Err(self.error(format!("Unexpected '{}' after padding", b as char)))
+                    }
+                    b => {
+                        return Err(self.error(format!(
+                            "Invalid {} character {:?}",
+                            if self.url_safe { "urlsafe" } else { "standard" },
+                            b as char
+                        )))
+                    }
+                }
+                buf[written] = b;
+                written += 1;
+            }
+            if written > 0 {
+                return Ok(written);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::get_reader;
+    use std::io::Cursor;
 
     #[test]
     fn test_encode() {
-        let input = "Cargo.toml";
+        let input = get_reader("Cargo.toml").unwrap();
         let format = Base64Format::Standard;
-        process_encode(input, format).unwrap();
+        process_encode(input, io::sink(), format, None).unwrap();
     }
 
     #[test]
     fn test_decode() {
-        let input = "fixtures/b64.txt";
+        let input = get_reader("fixtures/b64.txt").unwrap();
         let format = Base64Format::UrlSafe;
-        process_decode(input, format).unwrap();
+        process_decode(input, io::sink(), format, false).unwrap();
+    }
+
+    #[test]
+    fn test_encode_decode_binary_roundtrip() {
+        let data = (0..=255u8)
+            .cycle()
+            .take(3 * CHUNK_SIZE + 7)
+            .collect::<Vec<_>>();
+        for format in [
+            Base64Format::Standard,
+            Base64Format::StandardNoPad,
+            Base64Format::UrlSafe,
+            Base64Format::UrlSafePad,
+            Base64Format::Mime,
+        ] {
+            let mut encoded = Vec::new();
+            process_encode(Cursor::new(&data), &mut encoded, format, None).unwrap();
+            encoded.extend_from_slice(b"\n");
+            let mut decoded = Vec::new();
+            let n = process_decode(Cursor::new(&encoded), &mut decoded, format, false).unwrap();
+            assert_eq!(n, data.len() as u64);
+            assert_eq!(decoded, data);
+        }
+    }
+
+    #[test]
+    fn test_decode_trims_whitespace_only_at_ends() {
+        let mut decoded = Vec::new();
+        process_decode(
+            Cursor::new(" \naGVsbG8=\r\n"),
+            &mut decoded,
+            Base64Format::Standard,
+            false,
+        )
+        .unwrap();
+        assert_eq!(decoded, b"hello");
+
+        let ret = process_decode(
+            Cursor::new("aGVs\nbG8="),
+            io::sink(),
+            Base64Format::Standard,
+            false,
+        );
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_decode_lenient() {
+        let mut decoded = Vec::new();
+        process_decode(
+            Cursor::new("aGVs\r\n bG8\tgd29y\nbGQ"),
+            &mut decoded,
+            Base64Format::Standard,
+            true,
+        )
+        .unwrap();
+        assert_eq!(decoded, b"hello world");
+    }
+
+    #[test]
+    fn test_encode_wrap() {
+        let data = [0u8; 120];
+        let mut encoded = Vec::new();
+        process_encode(Cursor::new(&data), &mut encoded, Base64Format::Mime, None).unwrap();
+        let encoded = String::from_utf8(encoded).unwrap();
+        let lines = encoded.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[..2].iter().all(|l| l.len() == MIME_LINE_WIDTH));
+
+        let mut encoded = Vec::new();
+        process_encode(
+            Cursor::new(&data),
+            &mut encoded,
+            Base64Format::Standard,
+            Some(16),
+        )
+        .unwrap();
+        let encoded = String::from_utf8(encoded).unwrap();
+        assert_eq!(encoded.lines().count(), 10);
+        assert!(encoded.lines().all(|l| l.len() == 16));
+        assert!(!encoded.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_decode_auto_detects_format() {
+        let cases: [(&str, Base64Format); 5] = [
+            ("-_-_", Base64Format::UrlSafePad),
+            ("-_8", Base64Format::UrlSafe),
+            ("+/8=", Base64Format::Standard),
+            ("+/8", Base64Format::StandardNoPad),
+            ("aGVsbG8=", Base64Format::Standard),
+        ];
+        for (input, expected) in cases {
+            let (format, _) = process_decode_auto(Cursor::new(input), io::sink()).unwrap();
+            assert_eq!(format, expected, "{}", input);
+        }
+
+        let data = [0xfbu8; 120];
+        let mut encoded = Vec::new();
+        process_encode(Cursor::new(&data), &mut encoded, Base64Format::Mime, None).unwrap();
+        let mut decoded = Vec::new();
+        let (format, _) = process_decode_auto(Cursor::new(encoded), &mut decoded).unwrap();
+        assert_eq!(format, Base64Format::Mime);
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_decode_auto_reports_error_position() {
+        let err = process_decode_auto(Cursor::new("aGVs\nbG-8+"), io::sink()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid urlsafe character '+' at line 2, column 5 (offset 10)"
+        );
+
+        let err = process_decode_auto(Cursor::new("aGVsb"), io::sink()).unwrap_err();
+        assert!(err.to_string().starts_with("Truncated input"));
+    }
+
+    #[test]
+    fn test_data_uri_roundtrip() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let mut encoded = Vec::new();
+        let (mime, _) = process_encode_data_uri(Cursor::new(png), &mut encoded, "-").unwrap();
+        assert_eq!(mime, "image/png");
+        assert!(encoded.starts_with(b"data:image/png;base64,iVBORw0KGgo"));
+
+        let uri = process_decode_data_uri(Cursor::new(encoded)).unwrap();
+        assert_eq!(uri.mime, "image/png");
+        assert_eq!(uri.payload, png);
+        assert_eq!(data_uri_extension(&uri), "png");
+    }
+
+    #[test]
+    fn test_data_uri_sniffs_by_name_and_text() {
+        let mut encoded = Vec::new();
+        let (mime, _) =
+            process_encode_data_uri(Cursor::new("body {}"), &mut encoded, "style.css").unwrap();
+        assert_eq!(mime, "text/css");
+        let (mime, _) = process_encode_data_uri(Cursor::new("héllo"), io::sink(), "-").unwrap();
+        assert_eq!(mime, "text/plain");
+        let (mime, _) =
+            process_encode_data_uri(Cursor::new([0xff, 0xfe, 0x00]), io::sink(), "-").unwrap();
+        assert_eq!(mime, "application/octet-stream");
+    }
+
+    #[test]
+    fn test_decode_data_uri() {
+        let uri = process_decode_data_uri(Cursor::new("data:,Hello%2C%20World!")).unwrap();
+        assert_eq!(uri.mime, DATA_URI_DEFAULT_MIME);
+        assert_eq!(uri.payload, b"Hello, World!");
+        assert_eq!(data_uri_extension(&uri), "txt");
+
+        let uri =
+            process_decode_data_uri(Cursor::new("data:application/json;base64,e30=\n")).unwrap();
+        assert_eq!(uri.mime, "application/json");
+        assert_eq!(uri.payload, b"{}");
+        assert_eq!(data_uri_extension(&uri), "json");
+
+        assert!(process_decode_data_uri(Cursor::new("aGVsbG8=")).is_err());
+        assert!(process_decode_data_uri(Cursor::new("data:text/plain")).is_err());
+        assert!(process_decode_data_uri(Cursor::new("data:,%zz")).is_err());
+    }
+
+    #[test]
+    fn test_data_uri_output_path() {
+        let uri =
+            process_decode_data_uri(Cursor::new("data:application/json;base64,e30=")).unwrap();
+        assert_eq!(data_uri_output_path("-", &uri), Path::new("decoded.json"));
+        assert_eq!(
+            data_uri_output_path("assets/logo.txt", &uri),
+            Path::new("assets/logo.json")
+        );
+        assert_eq!(
+            data_uri_output_path("config.json", &uri),
+            Path::new("config.decoded.json")
+        );
     }
 }
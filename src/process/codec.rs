@@ -0,0 +1,512 @@
+use crate::cli::codec::CodecFormat;
+use crate::process::base64::LineWrapWriter;
+use anyhow::Result;
+use std::io::{Read, Write};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const HEX_ALPHABET: &[u8; 16] = b"0123456789abcdef";
+
+/// A binary-to-text codec that is fed the input chunk by chunk.
+///
+/// Implementations keep whatever partial block is left between two calls, so the
+/// same driver can stream any codec. Decoders skip ASCII whitespace.
+pub trait Codec {
+    fn encode_update(&mut self, input: &[u8], out: &mut Vec<u8>);
+    fn encode_finish(&mut self, out: &mut Vec<u8>);
+    fn decode_update(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<()>;
+    fn decode_finish(&mut self, out: &mut Vec<u8>) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct Hex {
+    nibble: Option<u8>,
+}
+
+#[derive(Default)]
+pub struct Base32 {
+    acc: u64,
+    bits: u32,
+    symbols: usize,
+    padding: bool,
+}
+
+/// Base58 with the bitcoin alphabet.
+///
+/// Base58 treats the whole input as one big number, so it can not be streamed:
+/// the input is buffered and converted when finished.
+#[derive(Default)]
+pub struct Base58 {
+    buf: Vec<u8>,
+}
+
+/// Adobe flavored ascii85, the output is enclosed in `<~` and `~>`
+#[derive(Default)]
+pub struct Ascii85 {
+    group: [u8; 5],
+    len: usize,
+    started: bool,
+    state: Ascii85State,
+}
+
+#[derive(Default, PartialEq, Eq)]
+enum Ascii85State {
+    #[default]
+    Start,
+    Prefix,
+    Data,
+    Tilde,
+    End,
+}
+
+/// Stream `reader` through the encoder of `format` into `writer`, returns the number of input bytes
+///
+/// The output is broken into lines of `wrap` characters if given.
+pub fn process_codec_encode(
+    reader: impl Read,
+    writer: impl Write,
+    format: CodecFormat,
+    wrap: Option<usize>,
+) -> Result<u64> {
+    let writer = LineWrapWriter::new(writer, wrap.unwrap_or(0), b"\n");
+    let (n, _) = transcode(
+        reader,
+        writer,
+        new_codec(format).as_mut(),
+        Direction::Encode,
+    )?;
+    Ok(n)
+}
+
+/// Stream encoded text from `reader` into raw bytes in `writer`, returns the number of decoded bytes
+pub fn process_codec_decode(
+    reader: impl Read,
+    writer: impl Write,
+    format: CodecFormat,
+) -> Result<u64> {
+    let (_, n) = transcode(
+        reader,
+        writer,
+        new_codec(format).as_mut(),
+        Direction::Decode,
+    )?;
+    Ok(n)
+}
+
+/// Encode a small in-memory buffer
+pub fn encode_bytes(data: &[u8], format: CodecFormat) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut codec = new_codec(format);
+    codec.encode_update(data, &mut out);
+    codec.encode_finish(&mut out);
+    out
+}
+
+/// Decode a small in-memory buffer
+pub fn decode_bytes(data: &[u8], format: CodecFormat) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut codec = new_codec(format);
+    codec.decode_update(data, &mut out)?;
+    codec.decode_finish(&mut out)?;
+    Ok(out)
+}
+
+fn new_codec(format: CodecFormat) -> Box<dyn Codec> {
+    match format {
+        CodecFormat::Base32 => Box::<Base32>::default(),
+        CodecFormat::Base58 => Box::<Base58>::default(),
+        CodecFormat::Hex => Box::<Hex>::default(),
+        CodecFormat::Ascii85 => Box::<Ascii85>::default(),
+    }
+}
+
+enum Direction {
+    Encode,
+    Decode,
+}
+
+/// Read `reader` in chunks, feed them to `codec` and write what it produced,
+/// returns the number of bytes read and written
+fn transcode(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    codec: &mut dyn Codec,
+    direction: Direction,
+) -> Result<(u64, u64)> {
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut out = Vec::with_capacity(CHUNK_SIZE * 2);
+    let (mut read, mut written) = (0, 0);
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        read += n as u64;
+        match direction {
+            Direction::Encode => codec.encode_update(&buffer[..n], &mut out),
+            Direction::Decode => codec.decode_update(&buffer[..n], &mut out)?,
+        }
+        writer.write_all(&out)?;
+        written += out.len() as u64;
+        out.clear();
+    }
+    match direction {
+        Direction::Encode => codec.encode_finish(&mut out),
+        Direction::Decode => codec.decode_finish(&mut out)?,
+    }
+    writer.write_all(&out)?;
+    writer.flush()?;
+    written += out.len() as u64;
+    Ok((read, written))
+}
+
+impl Codec for Hex {
+    fn encode_update(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        for &b in input {
+            out.push(HEX_ALPHABET[(b >> 4) as usize]);
+            out.push(HEX_ALPHABET[(b & 0x0f) as usize]);
+        }
+    }
+
+    fn encode_finish(&mut self, _out: &mut Vec<u8>) {}
+
+    fn decode_update(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        for &c in input.iter().filter(|c| !c.is_ascii_whitespace()) {
+            let v = match c {
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'f' => c - b'a' + 10,
+                b'A'..=b'F' => c - b'A' + 10,
+                _ => return Err(anyhow::anyhow!("Invalid hex character {:?}", c as char)),
+            };
+            match self.nibble.take() {
+                Some(high) => out.push(high << 4 | v),
+                None => self.nibble = Some(v),
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_finish(&mut self, _out: &mut Vec<u8>) -> Result<()> {
+        match self.nibble {
+            Some(_) => Err(anyhow::anyhow!("Odd number of hex digits")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Codec for Base32 {
+    fn encode_update(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        for &b in input {
+            self.acc = self.acc << 8 | b as u64;
+            self.bits += 8;
+            while self.bits >= 5 {
+                self.bits -= 5;
+                out.push(BASE32_ALPHABET[(self.acc >> self.bits & 0x1f) as usize]);
+                self.symbols += 1;
+            }
+        }
+    }
+
+    fn encode_finish(&mut self, out: &mut Vec<u8>) {
+        if self.bits > 0 {
+            out.push(BASE32_ALPHABET[(self.acc << (5 - self.bits) & 0x1f) as usize]);
+            self.symbols += 1;
+            self.bits = 0;
+        }
+        while !self.symbols.is_multiple_of(8) {
+            out.push(b'=');
+            self.symbols += 1;
+        }
+    }
+
+    /// Lowercase symbols and missing padding are accepted, as is common for TOTP secrets
+    fn decode_update(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        for &c in input.iter().filter(|c| !c.is_ascii_whitespace()) {
+            if c == b'=' {
+                self.padding = true;
+                continue;
+            }
+            if self.padding {
+                return Err(anyhow::anyhow!(
+                    "Unexpected base32 character {:?} after padding",
+                    c as char
+                ));
+            }
+            let v = match c.to_ascii_uppercase() {
+                c @ b'A'..=b'Z' => c - b'A',
+                c @ b'2'..=b'7' => c - b'2' + 26,
+                _ => return Err(anyhow::anyhow!("Invalid base32 character {:?}", c as char)),
+            };
+            self.acc = self.acc << 5 | v as u64;
+            self.bits += 5;
+            if self.bits >= 8 {
+                self.bits -= 8;
+                out.push((self.acc >> self.bits) as u8);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_finish(&mut self, _out: &mut Vec<u8>) -> Result<()> {
+        // A valid tail leaves less than a full byte of zero bits behind
+        if self.bits >= 5 || self.acc & ((1 << self.bits) - 1) != 0 {
+            return Err(anyhow::anyhow!("Invalid base32 length or trailing bits"));
+        }
+        Ok(())
+    }
+}
+
+impl Codec for Base58 {
+    fn encode_update(&mut self, input: &[u8], _out: &mut Vec<u8>) {
+        self.buf.extend_from_slice(input);
+    }
+
+    fn encode_finish(&mut self, out: &mut Vec<u8>) {
+        out.extend_from_slice(bs58::encode(&self.buf).into_string().as_bytes());
+    }
+
+    fn decode_update(&mut self, input: &[u8], _out: &mut Vec<u8>) -> Result<()> {
+        self.buf
+            .extend(input.iter().filter(|c| !c.is_ascii_whitespace()));
+        Ok(())
+    }
+
+    fn decode_finish(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        out.extend_from_slice(&bs58::decode(&self.buf).into_vec()?);
+        Ok(())
+    }
+}
+
+impl Ascii85 {
+    fn decode_group(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        let len = self.len;
+        // A partial group is padded with the highest digit and yields one byte less than its length
+        for d in &mut self.group[len..] {
+            *d = 84;
+        }
+        let value = self
+            .group
+            .iter()
+            .try_fold(0u32, |acc, &d| acc.checked_mul(85)?.checked_add(d as u32))
+            .ok_or_else(|| anyhow::anyhow!("Ascii85 group out of range"))?;
+        out.extend_from_slice(&value.to_be_bytes()[..len - 1]);
+        self.len = 0;
+        Ok(())
+    }
+
+    fn push_digit(&mut self, c: u8, out: &mut Vec<u8>) -> Result<()> {
+        self.state = Ascii85State::Data;
+        self.group[self.len] = c - b'!';
+        self.len += 1;
+        match self.len {
+            5 => self.decode_group(out),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Codec for Ascii85 {
+    fn encode_update(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        if !self.started {
+            out.extend_from_slice(b"<~");
+            self.started = true;
+        }
+        for &b in input {
+            self.group[self.len] = b;
+            self.len += 1;
+            if self.len == 4 {
+                encode_ascii85_group(&self.group[..4], out);
+                self.len = 0;
+            }
+        }
+    }
+
+    fn encode_finish(&mut self, out: &mut Vec<u8>) {
+        if !self.started {
+            out.extend_from_slice(b"<~");
+        }
+        if self.len > 0 {
+            encode_ascii85_group(&self.group[..self.len], out);
+        }
+        out.extend_from_slice(b"~>");
+    }
+
+    fn decode_update(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        for &c in input.iter().filter(|c| !c.is_ascii_whitespace()) {
+            // `<` is also a digit, it only starts the `<~` prefix when followed by `~`
+            if self.state == Ascii85State::Prefix && c != b'~' {
+                self.push_digit(b'<', out)?;
+            }
+            match (&self.state, c) {
+                (Ascii85State::Start, b'<') => self.state = Ascii85State::Prefix,
+                (Ascii85State::Prefix, b'~') => self.state = Ascii85State::Data,
+                (Ascii85State::Start | Ascii85State::Data, b'~') => {
+                    self.state = Ascii85State::Tilde
+                }
+                (Ascii85State::Tilde, b'>') => self.state = Ascii85State::End,
+                (Ascii85State::Start | Ascii85State::Data, b'z') if self.len == 0 => {
+                    self.state = Ascii85State::Data;
+                    out.extend_from_slice(&[0; 4]);
+                }
+                (Ascii85State::Start | Ascii85State::Data, b'!'..=b'u') => {
+                    self.push_digit(c, out)?;
+                }
+                (Ascii85State::End, _) => {
+                    return Err(anyhow::anyhow!("Unexpected data after ascii85 end marker"))
+                }
+                _ => return Err(anyhow::anyhow!("Invalid ascii85 character {:?}", c as char)),
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_finish(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        if self.state == Ascii85State::Prefix {
+            self.push_digit(b'<', out)?;
+        }
+        match self.len {
+            0 => Ok(()),
+            1 => Err(anyhow::anyhow!("Invalid ascii85 length")),
+            _ => self.decode_group(out),
+        }
+    }
+}
+
+/// Encode up to 4 bytes, a partial group of n bytes is zero padded and emits n + 1 digits
+fn encode_ascii85_group(group: &[u8], out: &mut Vec<u8>) {
+    let mut bytes = [0u8; 4];
+    bytes[..group.len()].copy_from_slice(group);
+    let mut value = u32::from_be_bytes(bytes);
+    if value == 0 && group.len() == 4 {
+        out.push(b'z');
+        return;
+    }
+    let mut digits = [0u8; 5];
+    for d in digits.iter_mut().rev() {
+        *d = (value % 85) as u8 + b'!';
+        value /= 85;
+    }
+    out.extend_from_slice(&digits[..group.len() + 1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+
+    const FORMATS: [CodecFormat; 4] = [
+        CodecFormat::Base32,
+        CodecFormat::Base58,
+        CodecFormat::Hex,
+        CodecFormat::Ascii85,
+    ];
+
+    fn encode(input: &[u8], format: CodecFormat) -> String {
+        let mut out = Vec::new();
+        process_codec_encode(Cursor::new(input), &mut out, format, None).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn decode(input: &str, format: CodecFormat) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        process_codec_decode(Cursor::new(input), &mut out, format)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_known_vectors() {
+        let cases: [(&[u8], &str, CodecFormat); 8] = [
+            (b"foobar", "MZXW6YTBOI======", CodecFormat::Base32),
+            (b"fooba", "MZXW6YTB", CodecFormat::Base32),
+            (b"Hello World!", "2NEpo7TZRRrLZSi2U", CodecFormat::Base58),
+            (b"\x00\x00\x01", "112", CodecFormat::Base58),
+            (b"\x01\xab\xff", "01abff", CodecFormat::Hex),
+            (b"Man ", "<~9jqo^~>", CodecFormat::Ascii85),
+            (b"\0\0\0\0ab", "<~z@:B~>", CodecFormat::Ascii85),
+            (b"", "<~~>", CodecFormat::Ascii85),
+        ];
+        for (data, text, format) in cases {
+            assert_eq!(encode(data, format), text);
+            assert_eq!(decode(text, format).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_across_chunks() {
+        let data = (0..=255u8)
+            .cycle()
+            .take(2 * CHUNK_SIZE + 3)
+            .collect::<Vec<_>>();
+        // Base58 is quadratic in the input size, keep its input small
+        for format in FORMATS {
+            let data = match format {
+                CodecFormat::Base58 => &data[..1024],
+                _ => &data,
+            };
+            let encoded = encode(data, format);
+            assert_eq!(decode(&encoded, format).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_encode_wrap() {
+        let mut out = Vec::new();
+        process_codec_encode(
+            Cursor::new([0xabu8; 20]),
+            &mut out,
+            CodecFormat::Hex,
+            Some(16),
+        )
+        .unwrap();
+        let encoded = String::from_utf8(out).unwrap();
+        assert_eq!(
+            encoded.lines().map(str::len).collect::<Vec<_>>(),
+            [16, 16, 8]
+        );
+        assert_eq!(decode(&encoded, CodecFormat::Hex).unwrap(), [0xab; 20]);
+    }
+
+    #[test]
+    fn test_decode_is_lenient_with_whitespace_and_case() {
+        assert_eq!(
+            decode("mzxw 6ytb\noi", CodecFormat::Base32).unwrap(),
+            b"foobar"
+        );
+        assert_eq!(
+            decode("01 AB\nff", CodecFormat::Hex).unwrap(),
+            b"\x01\xab\xff"
+        );
+        assert_eq!(decode("9jqo^", CodecFormat::Ascii85).unwrap(), b"Man ");
+    }
+
+    #[test]
+    fn test_decode_raw_ascii85_starting_with_less_than() {
+        let data = b"T\x02.\xe4ab";
+        let encoded = String::from_utf8(encode_bytes(data, CodecFormat::Ascii85)).unwrap();
+        let raw = encoded
+            .strip_prefix("<~")
+            .unwrap()
+            .strip_suffix("~>")
+            .unwrap();
+        assert!(raw.starts_with('<'));
+        assert_eq!(decode(raw, CodecFormat::Ascii85).unwrap(), data);
+        assert_eq!(decode(&encoded, CodecFormat::Ascii85).unwrap(), data);
+        // a lone `<` is a one digit group, too short to decode
+        assert!(decode("<", CodecFormat::Ascii85).is_err());
+    }
+
+    #[test]
+    fn test_decode_invalid_input_should_fail() {
+        assert!(decode("0g", CodecFormat::Hex).is_err());
+        assert!(decode("abc", CodecFormat::Hex).is_err());
+        assert!(decode("MZXW1", CodecFormat::Base32).is_err());
+        assert!(decode("MZ=XW", CodecFormat::Base32).is_err());
+        assert!(decode("0OIl", CodecFormat::Base58).is_err());
+        assert!(decode("<~9jqo^~>x", CodecFormat::Ascii85).is_err());
+        assert!(decode("s8W-\"", CodecFormat::Ascii85).is_err());
+        assert!(
+            process_codec_decode(Cursor::new("9jqo^v"), io::sink(), CodecFormat::Ascii85).is_err()
+        );
+    }
+}
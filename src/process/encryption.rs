@@ -0,0 +1,284 @@
+use crate::cli::text::TextCipher;
+use crate::process::text::Blake3;
+use anyhow::Result;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 7] = b"RCLIENC";
+const VERSION: u8 = 1;
+const CHUNK_SIZE: u32 = 64 * 1024;
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+const TAG_LEN: usize = 16;
+/// Context string used to derive the encryption key from a text key file
+const KEY_CONTEXT: &str = "rcli 2024 text encrypt v1";
+
+/// Header written in front of the ciphertext: magic, version, cipher id, chunk size and nonce prefix.
+///
+/// The header is authenticated as associated data of every chunk.
+struct Header {
+    cipher: TextCipher,
+    chunk_size: u32,
+    nonce_prefix: Vec<u8>,
+}
+
+enum Cipher {
+    XChaCha20Poly1305(XChaCha20Poly1305),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+/// Encrypt `reader` into `writer` with the key from a text (blake3) key file, returns the number of plaintext bytes
+///
+/// The input is split into chunks sealed with the STREAM construction: each chunk nonce is the random
+/// prefix from the header, a 32-bit big endian counter and a flag marking the last chunk, so reordered,
+/// dropped or truncated chunks fail authentication.
+pub fn process_encrypt(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    key: &Blake3,
+    cipher: TextCipher,
+) -> Result<u64> {
+    let mut nonce_prefix = vec![0u8; nonce_len(cipher) - 5];
+    OsRng.fill_bytes(&mut nonce_prefix);
+    let header = Header {
+        cipher,
+        chunk_size: CHUNK_SIZE,
+        nonce_prefix,
+    };
+    let aad = header.to_bytes();
+    writer.write_all(&aad)?;
+
+    let aead = Cipher::new(cipher, key);
+    let chunk_size = header.chunk_size as usize;
+    let mut current = vec![0u8; chunk_size];
+    let mut next = vec![0u8; chunk_size];
+    let mut len = read_full(&mut reader, &mut current)?;
+    let (mut counter, mut total) = (0u32, 0u64);
+    loop {
+        let next_len = read_full(&mut reader, &mut next)?;
+        let last = next_len == 0;
+        let nonce = header.nonce(counter, last)?;
+        let sealed = aead.seal(&nonce, &current[..len], &aad)?;
+        writer.write_all(&sealed)?;
+        total += len as u64;
+        if last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+        counter += 1;
+    }
+    writer.flush()?;
+    Ok(total)
+}
+
+/// Decrypt data produced by [`process_encrypt`], returns the number of plaintext bytes
+///
+/// Plaintext is written chunk by chunk once authenticated, so on failure the output may hold
+/// the chunks that were authentic before the failing one.
+pub fn process_decrypt(mut reader: impl Read, mut writer: impl Write, key: &Blake3) -> Result<u64> {
+    let header = Header::read(&mut reader)?;
+    let aad = header.to_bytes();
+    let aead = Cipher::new(header.cipher, key);
+
+    let chunk_size = header.chunk_size as usize + TAG_LEN;
+    let mut current = vec![0u8; chunk_size];
+    let mut next = vec![0u8; chunk_size];
+    let mut len = read_full(&mut reader, &mut current)?;
+    let (mut counter, mut total) = (0u32, 0u64);
+    loop {
+        let next_len = read_full(&mut reader, &mut next)?;
+        let last = next_len == 0;
+        let nonce = header.nonce(counter, last)?;
+        let plain = aead.open(&nonce, &current[..len], &aad)?;
+        writer.write_all(&plain)?;
+        total += plain.len() as u64;
+        if last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+        counter += 1;
+    }
+    writer.flush()?;
+    Ok(total)
+}
+
+impl Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(MAGIC.len() + 6 + self.nonce_prefix.len());
+        buf.extend_from_slice(MAGIC);
+        buf.push(VERSION);
+        buf.push(cipher_id(self.cipher));
+        buf.extend_from_slice(&self.chunk_size.to_be_bytes());
+        buf.extend_from_slice(&self.nonce_prefix);
+        buf
+    }
+
+    fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut fixed = [0u8; MAGIC.len() + 6];
+        reader
+            .read_exact(&mut fixed)
+            .map_err(|_| anyhow::anyhow!("Input is too short to be encrypted data"))?;
+        if &fixed[..MAGIC.len()] != MAGIC {
+            return Err(anyhow::anyhow!("Input is not rcli encrypted data"));
+        }
+        let version = fixed[MAGIC.len()];
+        if version != VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported encryption version: {}",
+                version
+            ));
+        }
+        let cipher = match fixed[MAGIC.len() + 1] {
+            1 => TextCipher::XChaCha20Poly1305,
+            2 => TextCipher::ChaCha20Poly1305,
+            id => return Err(anyhow::anyhow!("Unsupported cipher id: {}", id)),
+        };
+        let chunk_size = u32::from_be_bytes(fixed[MAGIC.len() + 2..].try_into()?);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(anyhow::anyhow!("Invalid chunk size: {}", chunk_size));
+        }
+        let mut nonce_prefix = vec![0u8; nonce_len(cipher) - 5];
+        reader.read_exact(&mut nonce_prefix)?;
+        Ok(Self {
+            cipher,
+            chunk_size,
+            nonce_prefix,
+        })
+    }
+
+    fn nonce(&self, counter: u32, last: bool) -> Result<Vec<u8>> {
+        if counter == u32::MAX {
+            return Err(anyhow::anyhow!("Input is too large to encrypt"));
+        }
+        let mut nonce = self.nonce_prefix.clone();
+        nonce.extend_from_slice(&counter.to_be_bytes());
+        nonce.push(last as u8);
+        Ok(nonce)
+    }
+}
+
+impl Cipher {
+    fn new(cipher: TextCipher, key: &Blake3) -> Self {
+        let key = blake3::derive_key(KEY_CONTEXT, key.as_bytes());
+        let key = GenericArray::from_slice(&key);
+        match cipher {
+            TextCipher::XChaCha20Poly1305 => Self::XChaCha20Poly1305(XChaCha20Poly1305::new(key)),
+            TextCipher::ChaCha20Poly1305 => Self::ChaCha20Poly1305(ChaCha20Poly1305::new(key)),
+        }
+    }
+
+    fn seal(&self, nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload { msg, aad };
+        match self {
+            Self::XChaCha20Poly1305(c) => c.encrypt(GenericArray::from_slice(nonce), payload),
+            Self::ChaCha20Poly1305(c) => c.encrypt(GenericArray::from_slice(nonce), payload),
+        }
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))
+    }
+
+    fn open(&self, nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload { msg, aad };
+        match self {
+            Self::XChaCha20Poly1305(c) => c.decrypt(GenericArray::from_slice(nonce), payload),
+            Self::ChaCha20Poly1305(c) => c.decrypt(GenericArray::from_slice(nonce), payload),
+        }
+        .map_err(|_| anyhow::anyhow!("Authentication failed: wrong key or tampered/truncated data"))
+    }
+}
+
+fn cipher_id(cipher: TextCipher) -> u8 {
+    match cipher {
+        TextCipher::XChaCha20Poly1305 => 1,
+        TextCipher::ChaCha20Poly1305 => 2,
+    }
+}
+
+fn nonce_len(cipher: TextCipher) -> usize {
+    match cipher {
+        TextCipher::XChaCha20Poly1305 => 24,
+        TextCipher::ChaCha20Poly1305 => 12,
+    }
+}
+
+/// Fill `buf` as far as possible, returns less than its length only at the end of the input
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CIPHERS: [TextCipher; 2] = [TextCipher::XChaCha20Poly1305, TextCipher::ChaCha20Poly1305];
+
+    fn key() -> Blake3 {
+        Blake3::from_file("fixtures/blake3.key").unwrap()
+    }
+
+    fn encrypt(data: &[u8], cipher: TextCipher) -> Vec<u8> {
+        let mut out = Vec::new();
+        process_encrypt(Cursor::new(data), &mut out, &key(), cipher).unwrap();
+        out
+    }
+
+    fn decrypt(data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        process_decrypt(Cursor::new(data), &mut out, &key())?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let chunk = CHUNK_SIZE as usize;
+        for cipher in CIPHERS {
+            for len in [0, 1, chunk, 2 * chunk, 2 * chunk + 5] {
+                let data = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+                let encrypted = encrypt(&data, cipher);
+                assert_eq!(decrypt(&encrypted).unwrap(), data, "{:?} {}", cipher, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decrypt_detects_tampering() {
+        let data = vec![42u8; CHUNK_SIZE as usize * 2 + 10];
+        let encrypted = encrypt(&data, TextCipher::XChaCha20Poly1305);
+
+        let mut flipped = encrypted.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&flipped).is_err());
+
+        // Dropping the last chunk must not pass for a shorter valid message
+        let header_len = MAGIC.len() + 6 + 19;
+        let truncated = &encrypted[..header_len + 2 * (CHUNK_SIZE as usize + TAG_LEN)];
+        assert!(decrypt(truncated).is_err());
+
+        let mut header = encrypted.clone();
+        header[MAGIC.len() + 1] = 2;
+        assert!(decrypt(&header).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key_should_fail() {
+        let encrypted = encrypt(b"hello", TextCipher::ChaCha20Poly1305);
+        let mut out = Vec::new();
+        let wrong = Blake3::new([1; 32]);
+        assert!(process_decrypt(Cursor::new(&encrypted), &mut out, &wrong).is_err());
+        assert!(decrypt(b"not encrypted").is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use crate::cli::text::{EnvelopeFormat, TextSignFormat};
+use crate::process::text::{process_sign_with_key_id, verify_with_key_id};
+use crate::process::verify::{VerifyFailure, WithFailure};
+use anyhow::Result;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const ENVELOPE_VERSION: u32 = 1;
+const COMPACT_PREFIX: &str = "rcli-sig";
+
+/// Self-describing signature recording the algorithm and the key that produced it
+///
+/// The compact text form is `rcli-sig:<version>:<algorithm>:<key id>:<created>:<signature>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureEnvelope {
+    pub version: u32,
+    pub algorithm: String,
+    /// Key id of the signing key, a prefix of its BLAKE3 fingerprint
+    pub key_id: String,
+    /// Unix timestamp of the signature
+    pub created: i64,
+    /// Signature, base64url encoded without padding
+    pub signature: String,
+}
+
+impl SignatureEnvelope {
+    pub fn new(format: TextSignFormat, key_id: String, created: i64, sig: &[u8]) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            algorithm: format.to_string(),
+            key_id,
+            created,
+            signature: BASE64_URL_SAFE_NO_PAD.encode(sig),
+        }
+    }
+
+    /// Header fields signed along with the message, so none of them can be changed afterwards
+    pub fn signed_header(&self) -> Vec<u8> {
+        signed_header(self.version, &self.algorithm, &self.key_id, self.created)
+    }
+
+    pub fn format(&self) -> Result<TextSignFormat> {
+        TextSignFormat::from_str(&self.algorithm)
+            .map_err(|_| anyhow::anyhow!("Unknown envelope algorithm: {}", self.algorithm))
+    }
+
+    pub fn signature_bytes(&self) -> Result<Vec<u8>> {
+        Ok(BASE64_URL_SAFE_NO_PAD.decode(&self.signature)?)
+    }
+
+    pub fn encode(&self, format: EnvelopeFormat) -> Result<String> {
+        match format {
+            EnvelopeFormat::Json => Ok(serde_json::to_string(self)?),
+            EnvelopeFormat::Compact => Ok(format!(
+                "{}:{}:{}:{}:{}:{}",
+                COMPACT_PREFIX,
+                self.version,
+                self.algorithm,
+                self.key_id,
+                self.created,
+                self.signature
+            )),
+        }
+    }
+
+    /// Parse a JSON or compact envelope, `None` when `data` does not look like an envelope at all
+    pub fn detect(data: &[u8]) -> Option<Result<Self>> {
+        let text = std::str::from_utf8(data).ok()?.trim();
+        if text.starts_with('{') {
+            return Some(Self::parse_json(text));
+        }
+        if text.starts_with(COMPACT_PREFIX) && text[COMPACT_PREFIX.len()..].starts_with(':') {
+            return Some(Self::parse_compact(text));
+        }
+        None
+    }
+
+    fn parse_json(text: &str) -> Result<Self> {
+        let envelope: Self = serde_json::from_str(text)?;
+        envelope.check_version()
+    }
+
+    fn parse_compact(text: &str) -> Result<Self> {
+        let fields = text.split(':').collect::<Vec<_>>();
+        let [_, version, algorithm, key_id, created, signature] = fields[..] else {
+            return Err(anyhow::anyhow!(
+                "Invalid compact envelope: expected 6 ':' separated fields"
+            ));
+        };
+        let envelope = Self {
+            version: version.parse()?,
+            algorithm: algorithm.to_string(),
+            key_id: key_id.to_string(),
+            created: created.parse()?,
+            signature: signature.to_string(),
+        };
+        envelope.check_version()
+    }
+
+    fn check_version(self) -> Result<Self> {
+        match self.version {
+            ENVELOPE_VERSION => Ok(self),
+            v => Err(anyhow::anyhow!("Unsupported envelope version {}", v)),
+        }
+    }
+}
+
+/// Canonical encoding of the envelope header, one `name=value` line per field.
+/// The algorithm and key id cannot contain a line break: the first is a known format name,
+/// the second must match the hex key id of the verifying key.
+fn signed_header(version: u32, algorithm: &str, key_id: &str, created: i64) -> Vec<u8> {
+    format!(
+        "{}\nversion={}\nalgorithm={}\nkey_id={}\ncreated={}\n\n",
+        COMPACT_PREFIX, version, algorithm, key_id, created
+    )
+    .into_bytes()
+}
+
+/// Sign the envelope header followed by `input` and wrap the signature in an envelope
+pub fn process_sign_envelope(
+    input: &str,
+    key: &str,
+    format: TextSignFormat,
+    context: Option<&str>,
+) -> Result<SignatureEnvelope> {
+    let created = chrono::Utc::now().timestamp();
+    let header = |key_id: &str| signed_header(ENVELOPE_VERSION, format.into(), key_id, created);
+    let (sig, key_id) = process_sign_with_key_id(input, key, format, context, header)?;
+    Ok(SignatureEnvelope::new(format, key_id, created, &sig))
+}
+
+/// Verify an envelope with the verifier of its algorithm, `key` must be the key it names and
+/// the signature must cover its header
+pub fn process_verify_envelope(
+    input: &str,
+    key: &str,
+    envelope: &SignatureEnvelope,
+    context: Option<&str>,
+) -> Result<bool> {
+    let format = envelope
+        .format()
+        .with_failure(VerifyFailure::MalformedInput)?;
+    let sig = envelope
+        .signature_bytes()
+        .with_failure(VerifyFailure::MalformedInput)?;
+    verify_with_key_id(
+        input,
+        key,
+        &sig,
+        format,
+        context,
+        Some(&envelope.key_id),
+        &envelope.signed_header(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::verify::VerifyError;
+
+    #[test]
+    fn test_envelope_sign_verify() -> Result<()> {
+        let envelope = process_sign_envelope(
+            "Cargo.toml",
+            "fixtures/secret.key",
+            TextSignFormat::Ed25519,
+            None,
+        )?;
+        assert_eq!(envelope.algorithm, "ed25519");
+        for format in [EnvelopeFormat::Json, EnvelopeFormat::Compact] {
+            let encoded = envelope.encode(format)?;
+            let decoded = SignatureEnvelope::detect(encoded.as_bytes()).unwrap()?;
+            assert_eq!(decoded, envelope);
+            assert!(process_verify_envelope(
+                "Cargo.toml",
+                "fixtures/public.key",
+                &decoded,
+                None
+            )?);
+            assert!(!process_verify_envelope(
+                "README.md",
+                "fixtures/public.key",
+                &decoded,
+                None
+            )?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_envelope_header_is_signed() -> Result<()> {
+        for format in [TextSignFormat::Ed25519, TextSignFormat::Blake3] {
+            let key = match format {
+                TextSignFormat::Ed25519 => ("fixtures/secret.key", "fixtures/public.key"),
+                _ => ("fixtures/blake3.key", "fixtures/blake3.key"),
+            };
+            let envelope = process_sign_envelope("Cargo.toml", key.0, format, None)?;
+            assert!(process_verify_envelope(
+                "Cargo.toml",
+                key.1,
+                &envelope,
+                None
+            )?);
+            let tampered = SignatureEnvelope {
+                created: envelope.created - 3600,
+                ..envelope
+            };
+            assert!(!process_verify_envelope(
+                "Cargo.toml",
+                key.1,
+                &tampered,
+                None
+            )?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_envelope_key_mismatch() -> Result<()> {
+        let envelope = process_sign_envelope(
+            "Cargo.toml",
+            "fixtures/blake3.key",
+            TextSignFormat::Blake3,
+            None,
+        )?;
+        let err = process_verify_envelope(
+            "Cargo.toml",
+            "fixtures/blake3.key",
+            &envelope,
+            Some("other context"),
+        )
+        .unwrap_err();
+        let err = err.downcast_ref::<VerifyError>().unwrap();
+        assert_eq!(err.failure, VerifyFailure::KeyError);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_envelope() {
+        assert!(SignatureEnvelope::detect(b"vssyI-rtjqysBvVtAHF4KDhFZkPBd5w").is_none());
+        assert!(SignatureEnvelope::detect(b"rcli-sig:1:ed25519:00")
+            .unwrap()
+            .is_err());
+        assert!(SignatureEnvelope::detect(b"rcli-sig:2:ed25519:00:0:AA")
+            .unwrap()
+            .is_err());
+    }
+}
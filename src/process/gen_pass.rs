@@ -1,10 +1,15 @@
-use rand::{seq::SliceRandom, thread_rng};
+use rand::rngs::{OsRng, StdRng};
+use rand::{seq::SliceRandom, CryptoRng, Rng, SeedableRng};
 
 const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
 const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const NUMBERS: &[u8] = b"0123456789";
 const SPECIAL: &[u8] = b"!@#$%&";
 
+/// z-score of the 99.9th percentile of the standard normal distribution,
+/// used to derive the chi-square critical value of the self test
+const SELF_TEST_Z: f64 = 3.090_232;
+
 pub fn generate_password(
     length: u8,
     lowercase: bool,
@@ -12,46 +17,201 @@ pub fn generate_password(
     numbers: bool,
     special: bool,
 ) -> anyhow::Result<String> {
-    let mut rng = thread_rng();
-    let mut chars = Vec::new();
-    let mut has_valid_option = false;
+    generate_password_with_rng(&mut OsRng, length, lowercase, uppercase, numbers, special)
+}
+
+/// Generate a password drawing all randomness from the given rng.
+///
+/// Production code goes through [`generate_password`], which uses the OS CSPRNG,
+/// tests can inject a seeded rng to get deterministic output.
+pub fn generate_password_with_rng<R: Rng + CryptoRng + ?Sized>(
+    rng: &mut R,
+    length: u8,
+    lowercase: bool,
+    uppercase: bool,
+    numbers: bool,
+    special: bool,
+) -> anyhow::Result<String> {
+    let classes = char_classes(lowercase, uppercase, numbers, special);
+    if (length as usize) < classes.len() {
+        return Err(anyhow::anyhow!(
+            "Password length must be at least {}",
+            classes.len()
+        ));
+    }
+
+    let chars = classes.concat();
     let mut password = Vec::with_capacity(length as usize);
 
-    if lowercase {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).unwrap());
-        has_valid_option = true;
+    // Make sure every selected class is present at least once
+    for class in &classes {
+        password.push(*class.choose(rng).unwrap());
     }
 
-    if uppercase {
-        chars.extend_from_slice(UPPER);
-        password.push(*UPPER.choose(&mut rng).unwrap());
-        has_valid_option = true;
+    for _ in 0..length - password.len() as u8 {
+        password.push(*chars.choose(rng).unwrap());
     }
 
+    password.shuffle(rng);
+
+    Ok(String::from_utf8(password)?)
+}
+
+fn char_classes(
+    lowercase: bool,
+    uppercase: bool,
+    numbers: bool,
+    special: bool,
+) -> Vec<&'static [u8]> {
+    let mut classes = Vec::with_capacity(4);
+    if lowercase {
+        classes.push(LOWER);
+    }
+    if uppercase {
+        classes.push(UPPER);
+    }
     if numbers {
-        chars.extend_from_slice(NUMBERS);
-        password.push(*NUMBERS.choose(&mut rng).unwrap());
-        has_valid_option = true;
+        classes.push(NUMBERS);
     }
-
     if special {
-        chars.extend_from_slice(SPECIAL);
-        password.push(*SPECIAL.choose(&mut rng).unwrap());
-        has_valid_option = true;
+        classes.push(SPECIAL);
     }
 
     // If no options selected, default to lowercase
-    if !has_valid_option {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).unwrap());
+    if classes.is_empty() {
+        classes.push(LOWER);
     }
+    classes
+}
 
-    for _ in 0..length - password.len() as u8 {
-        password.push(*chars.choose(&mut rng).unwrap());
+#[derive(Debug)]
+pub struct ClassStat {
+    pub name: &'static str,
+    pub observed: u64,
+    pub expected: f64,
+}
+
+#[derive(Debug)]
+pub struct SelfTestReport {
+    pub samples: usize,
+    pub classes: Vec<ClassStat>,
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+    pub critical_value: f64,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.chi_square <= self.critical_value
+    }
+}
+
+/// Generate `samples` passwords with every character class enabled and run a
+/// chi-square goodness-of-fit test over the frequency of every character.
+///
+/// The first character of each class is forced, so the expected frequency of a
+/// character `c` in class `C` per password is `1 / |C| + (length - classes) / |charset|`.
+/// The test fails at a significance level of 0.1%.
+pub fn self_test(samples: usize, length: u8, seed: Option<u64>) -> anyhow::Result<SelfTestReport> {
+    match seed {
+        Some(seed) => self_test_with_rng(&mut StdRng::seed_from_u64(seed), samples, length),
+        None => self_test_with_rng(&mut OsRng, samples, length),
     }
+}
 
-    password.shuffle(&mut rng);
+pub fn self_test_with_rng<R: Rng + CryptoRng + ?Sized>(
+    rng: &mut R,
+    samples: usize,
+    length: u8,
+) -> anyhow::Result<SelfTestReport> {
+    if samples == 0 {
+        return Err(anyhow::anyhow!("Number of samples must be positive"));
+    }
 
-    Ok(String::from_utf8(password)?)
+    let classes = char_classes(true, true, true, true);
+    let names = ["lowercase", "uppercase", "numbers", "special"];
+    let charset_len = classes.iter().map(|c| c.len()).sum::<usize>();
+
+    let mut counts = [0u64; 256];
+    for _ in 0..samples {
+        let password = generate_password_with_rng(rng, length, true, true, true, true)?;
+        for b in password.bytes() {
+            counts[b as usize] += 1;
+        }
+    }
+
+    let free = (length as usize - classes.len()) as f64;
+    let mut chi_square = 0.0;
+    let mut stats = Vec::with_capacity(classes.len());
+    for (class, name) in classes.iter().zip(names) {
+        let expected = samples as f64 * (1.0 / class.len() as f64 + free / charset_len as f64);
+        let mut observed = 0;
+        for &c in class.iter() {
+            let count = counts[c as usize];
+            observed += count;
+            chi_square += (count as f64 - expected).powi(2) / expected;
+        }
+        stats.push(ClassStat {
+            name,
+            observed,
+            expected: expected * class.len() as f64,
+        });
+    }
+
+    let degrees_of_freedom = charset_len - 1;
+    Ok(SelfTestReport {
+        samples,
+        classes: stats,
+        chi_square,
+        degrees_of_freedom,
+        critical_value: chi_square_critical(degrees_of_freedom),
+    })
+}
+
+/// Wilson-Hilferty approximation of the chi-square quantile
+fn chi_square_critical(df: usize) -> f64 {
+    let df = df as f64;
+    let t = 2.0 / (9.0 * df);
+    df * (1.0 - t + SELF_TEST_Z * t.sqrt()).powi(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_password_is_deterministic_with_seed() {
+        let mut rng1 = StdRng::seed_from_u64(42);
+        let mut rng2 = StdRng::seed_from_u64(42);
+        let p1 = generate_password_with_rng(&mut rng1, 16, true, true, true, true).unwrap();
+        let p2 = generate_password_with_rng(&mut rng2, 16, true, true, true, true).unwrap();
+        assert_eq!(p1, p2);
+        assert_eq!(p1.len(), 16);
+    }
+
+    #[test]
+    fn test_generate_password_contains_every_class() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let p = generate_password_with_rng(&mut rng, 4, true, true, true, true).unwrap();
+            assert!(p.bytes().any(|b| LOWER.contains(&b)));
+            assert!(p.bytes().any(|b| UPPER.contains(&b)));
+            assert!(p.bytes().any(|b| NUMBERS.contains(&b)));
+            assert!(p.bytes().any(|b| SPECIAL.contains(&b)));
+        }
+    }
+
+    #[test]
+    fn test_generate_password_too_short_should_fail() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(generate_password_with_rng(&mut rng, 3, true, true, true, true).is_err());
+    }
+
+    #[test]
+    fn test_self_test_should_pass() {
+        let report = self_test(10_000, 16, Some(1)).unwrap();
+        assert!(report.passed(), "{:?}", report);
+        let total = report.classes.iter().map(|c| c.observed).sum::<u64>();
+        assert_eq!(total, 10_000 * 16);
+    }
 }
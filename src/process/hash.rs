@@ -0,0 +1,329 @@
+use crate::cli::codec::CodecFormat;
+use crate::cli::hash::HashAlgorithm;
+use crate::process::codec::decode_bytes;
+use crate::utils::get_reader;
+use anyhow::Result;
+use sha2::{Digest, Sha256, Sha512};
+use sha3::{Sha3_256, Sha3_512};
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use xxhash_rust::xxh3::Xxh3;
+use xxhash_rust::xxh64::Xxh64;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Mismatch,
+    Unreadable(String),
+}
+
+/// Result of verifying one checksum manifest
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub entries: Vec<(String, CheckStatus)>,
+    pub malformed: usize,
+}
+
+/// Streaming digest over all supported algorithms
+enum Hasher {
+    Blake3(Box<blake3::Hasher>, usize),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Sha3_256(Sha3_256),
+    Sha3_512(Sha3_512),
+    Xxh64(Xxh64),
+    Xxh3(Box<Xxh3>),
+    Xxh3_128(Box<Xxh3>),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgorithm, length: Option<usize>) -> Self {
+        match algo {
+            HashAlgorithm::Blake3 => {
+                Self::Blake3(Box::default(), length.unwrap_or(blake3::OUT_LEN))
+            }
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+            HashAlgorithm::Sha3_256 => Self::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::Sha3_512 => Self::Sha3_512(Sha3_512::new()),
+            HashAlgorithm::Xxh64 => Self::Xxh64(Xxh64::new(0)),
+            HashAlgorithm::Xxh3 => Self::Xxh3(Box::default()),
+            HashAlgorithm::Xxh3_128 => Self::Xxh3_128(Box::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(h, _) => {
+                h.update(data);
+            }
+            Self::Sha256(h) => h.update(data),
+            Self::Sha512(h) => h.update(data),
+            Self::Sha3_256(h) => h.update(data),
+            Self::Sha3_512(h) => h.update(data),
+            Self::Xxh64(h) => h.update(data),
+            Self::Xxh3(h) | Self::Xxh3_128(h) => h.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Blake3(h, length) => {
+                let mut out = vec![0; length];
+                h.finalize_xof().fill(&mut out);
+                out
+            }
+            Self::Sha256(h) => h.finalize().to_vec(),
+            Self::Sha512(h) => h.finalize().to_vec(),
+            Self::Sha3_256(h) => h.finalize().to_vec(),
+            Self::Sha3_512(h) => h.finalize().to_vec(),
+            Self::Xxh64(h) => h.digest().to_be_bytes().to_vec(),
+            Self::Xxh3(h) => h.digest().to_be_bytes().to_vec(),
+            Self::Xxh3_128(h) => h.digest128().to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// Stream `reader` through `algo`, `length` sets the blake3 XOF output length in bytes
+pub fn hash_reader(
+    mut reader: impl Read,
+    algo: HashAlgorithm,
+    length: Option<usize>,
+) -> Result<Vec<u8>> {
+    let mut hasher = Hasher::new(algo, length);
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Hash files in parallel, results are in the order of `files`
+pub fn hash_files(
+    files: &[String],
+    algo: HashAlgorithm,
+    length: Option<usize>,
+) -> Vec<Result<Vec<u8>>> {
+    parallel_map(files, |file| hash_reader(get_reader(file)?, algo, length))
+}
+
+/// Format a `sha256sum` compatible line, names with a backslash or newline are escaped the GNU way
+pub fn format_checksum_line(hex: &str, name: &str) -> String {
+    if name.contains(['\\', '\n', '\r']) {
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+        format!("\\{}  {}", hex, escaped)
+    } else {
+        format!("{}  {}", hex, name)
+    }
+}
+
+/// Parse a `sha256sum` line (text or `*` binary mode), returns the digest and the file name
+pub fn parse_checksum_line(line: &str) -> Option<(Vec<u8>, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (hex, rest) = line.split_once(' ')?;
+    let name = rest.strip_prefix([' ', '*'])?;
+    if name.is_empty() {
+        return None;
+    }
+    let digest = decode_bytes(hex.as_bytes(), CodecFormat::Hex).ok()?;
+    if digest.is_empty() {
+        return None;
+    }
+    let name = match escaped {
+        true => unescape_name(name)?,
+        false => name.to_string(),
+    };
+    Some((digest, name))
+}
+
+/// Verify every file listed in a checksum manifest, the files are hashed in parallel
+///
+/// For blake3 the expected output length is taken from each digest so XOF checksums verify too.
+pub fn process_check(manifest: &str, algo: HashAlgorithm) -> Result<CheckReport> {
+    let mut report = CheckReport::default();
+    let mut entries = Vec::new();
+    let reader = get_reader(manifest).map_err(|e| anyhow::anyhow!("{}: {}", manifest, e))?;
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_checksum_line(line) {
+            Some(entry) => entries.push(entry),
+            None => report.malformed += 1,
+        }
+    }
+    if entries.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{}: no properly formatted checksum lines found",
+            manifest
+        ));
+    }
+
+    report.entries = parallel_map(&entries, |(expected, name)| {
+        let length = match algo {
+            HashAlgorithm::Blake3 => Some(expected.len()),
+            _ => None,
+        };
+        let status = match std::fs::File::open(name)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| hash_reader(file, algo, length))
+        {
+            Ok(digest) if &digest == expected => CheckStatus::Ok,
+            Ok(_) => CheckStatus::Mismatch,
+            Err(e) => CheckStatus::Unreadable(e.to_string()),
+        };
+        (name.clone(), status)
+    });
+    Ok(report)
+}
+
+fn unescape_name(name: &str) -> Option<String> {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => out.push('\\'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Run `f` over `items` on a pool of scoped threads, keeping the input order
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results = std::thread::scope(|s| {
+        let handles = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break done;
+                        };
+                        done.push((i, f(item)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("hash worker panicked"))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::codec::encode_bytes;
+
+    fn hex(algo: HashAlgorithm, data: &[u8], length: Option<usize>) -> String {
+        let digest = hash_reader(data, algo, length).unwrap();
+        String::from_utf8(encode_bytes(&digest, CodecFormat::Hex)).unwrap()
+    }
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            hex(HashAlgorithm::Sha256, b"abc", None),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(HashAlgorithm::Sha3_256, b"abc", None),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            hex(HashAlgorithm::Blake3, b"", None),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(hex(HashAlgorithm::Xxh64, b"", None), "ef46db3751d8e999");
+        assert_eq!(hex(HashAlgorithm::Sha512, b"abc", None).len(), 128);
+        assert_eq!(hex(HashAlgorithm::Xxh3_128, b"abc", None).len(), 32);
+    }
+
+    #[test]
+    fn test_blake3_xof_length() {
+        let short = hex(HashAlgorithm::Blake3, b"abc", None);
+        let long = hex(HashAlgorithm::Blake3, b"abc", Some(64));
+        assert_eq!(long.len(), 128);
+        assert!(long.starts_with(&short));
+    }
+
+    #[test]
+    fn test_checksum_line_roundtrip() {
+        let line = format_checksum_line("ab01", "a\\b\nc");
+        assert_eq!(line, "\\ab01  a\\\\b\\nc");
+        assert_eq!(
+            parse_checksum_line(&line),
+            Some((vec![0xab, 0x01], "a\\b\nc".to_string()))
+        );
+        assert_eq!(
+            parse_checksum_line("ab01 *binary.bin"),
+            Some((vec![0xab, 0x01], "binary.bin".to_string()))
+        );
+        assert_eq!(parse_checksum_line("ab01"), None);
+        assert_eq!(parse_checksum_line("zz  file"), None);
+    }
+
+    #[test]
+    fn test_hash_files_and_check() {
+        let files = vec!["Cargo.toml".to_string(), "README.md".to_string()];
+        let digests = hash_files(&files, HashAlgorithm::Blake3, Some(40));
+        let mut manifest = String::new();
+        for (file, digest) in files.iter().zip(digests) {
+            let hex = encode_bytes(&digest.unwrap(), CodecFormat::Hex);
+            manifest.push_str(&format_checksum_line(
+                std::str::from_utf8(&hex).unwrap(),
+                file,
+            ));
+            manifest.push('\n');
+        }
+        manifest.push_str("not a checksum line\n");
+        manifest.push_str(&format_checksum_line(&"00".repeat(40), "Cargo.lock"));
+        manifest.push_str("\n0000  missing-file\n");
+
+        let path = std::env::temp_dir().join(format!("rcli-sums-{}", std::process::id()));
+        std::fs::write(&path, manifest).unwrap();
+        let report = process_check(path.to_str().unwrap(), HashAlgorithm::Blake3).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(report.malformed, 1);
+        let statuses = report.entries.iter().map(|(_, s)| s).collect::<Vec<_>>();
+        assert_eq!(
+            statuses[..3],
+            [&CheckStatus::Ok, &CheckStatus::Ok, &CheckStatus::Mismatch]
+        );
+        assert!(matches!(statuses[3], CheckStatus::Unreadable(_)));
+    }
+}
@@ -1,32 +1,202 @@
-use jsonwebtoken::{DecodingKey, EncodingKey, TokenData};
+use crate::cli::jwt::JwtAlgorithm;
+use crate::process::key_encryption::read_secret_key_file;
+use crate::process::key_format::{
+    decode_ec_public_key, decode_ec_secret_key, decode_signing_key, decode_verifying_key,
+};
+use crate::process::verify::{VerifyError, VerifyFailure, WithFailure};
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Local, Utc};
+use ed25519_dalek::pkcs8::spki::SubjectPublicKeyInfoRef;
+use ed25519_dalek::pkcs8::{EncodePrivateKey, ObjectIdentifier, PrivateKeyInfo};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::io::Read;
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct JwtClaims {
-    pub(crate) aud: String,
-    pub(crate) sub: String,
-    pub(crate) iat: u64,
-    pub(crate) exp: u64,
-}
-
-pub fn process_jwt_sign(aud: &str, sub: &str, exp: &str, key: &str) -> anyhow::Result<String> {
-    let current_timestamp = jsonwebtoken::get_current_timestamp();
-    let claims = JwtClaims {
-        aud: aud.to_string(),
-        sub: sub.to_string(),
-        iat: current_timestamp,
-        exp: current_timestamp + parse_exp_duration(exp)?,
-    };
+/// Claim set of a token, the registered claims (`iss`, `sub`, `aud`, `exp`, `nbf`, `iat`, `jti`)
+/// and any custom ones
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JwtClaims(Map<String, Value>);
+
+impl JwtClaims {
+    /// Claims for `aud` and `sub` issued now and expiring after the `exp` duration
+    pub fn new(aud: &str, sub: &str, exp: &str) -> anyhow::Result<Self> {
+        let mut claims = Self::default();
+        claims.insert("aud", aud);
+        claims.insert("sub", sub);
+        claims.set_validity(exp)?;
+        Ok(claims)
+    }
+
+    /// Load the claims of a JSON object
+    pub fn from_reader(reader: impl Read) -> anyhow::Result<Self> {
+        match serde_json::from_reader(reader)? {
+            Value::Object(claims) => Ok(Self(claims)),
+            _ => Err(anyhow::anyhow!("Claims must be a JSON object")),
+        }
+    }
 
-    let jwt = jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(key.as_ref()),
-    )?;
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    /// Set `iat` to now and `exp` to the `exp` duration from now
+    pub fn set_validity(&mut self, exp: &str) -> anyhow::Result<()> {
+        let now = jsonwebtoken::get_current_timestamp();
+        self.insert("iat", now);
+        self.insert("exp", after(now, exp)?);
+        Ok(())
+    }
+
+    /// Set `nbf` to the `nbf` duration from now
+    pub fn set_not_before(&mut self, nbf: &str) -> anyhow::Result<()> {
+        let now = jsonwebtoken::get_current_timestamp();
+        self.insert("nbf", after(now, nbf)?);
+        Ok(())
+    }
+
+    /// Time of a numeric date claim such as `exp`
+    pub fn time(&self, name: &str) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.get(name)?.as_i64()?, 0)
+    }
+}
+
+/// Registered claims set by their own `jwt sign` option, which `--claim` cannot override
+const REGISTERED_CLAIMS: [&str; 7] = ["iss", "sub", "aud", "exp", "nbf", "iat", "jti"];
+
+/// Parse a `name=value` custom claim, the value is taken as JSON when it parses and as a string
+/// otherwise. Registered claims are rejected, they have their own options.
+pub fn parse_claim(s: &str) -> anyhow::Result<(String, Value)> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Claim must be name=value: {}", s))?;
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Claim name is empty: {}", s));
+    }
+    if REGISTERED_CLAIMS.contains(&name) {
+        return Err(anyhow::anyhow!(
+            "{} is a registered claim, set it with --{}",
+            name,
+            match name {
+                "iat" => "exp",
+                _ => name,
+            }
+        ));
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((name.to_string(), value))
+}
+
+/// Random token id, 128 bits encoded as base64url
+pub fn generate_jti() -> String {
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    BASE64_URL_SAFE_NO_PAD.encode(id)
+}
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(alg: JwtAlgorithm) -> Self {
+        match alg {
+            JwtAlgorithm::Hs256 => Algorithm::HS256,
+            JwtAlgorithm::Rs256 => Algorithm::RS256,
+            JwtAlgorithm::Es256 => Algorithm::ES256,
+            JwtAlgorithm::EdDsa => Algorithm::EdDSA,
+        }
+    }
+}
+
+/// Sign the claims with `alg`, `key` is the HS256 secret itself or the path of a secret key file
+pub fn process_jwt_sign(
+    claims: &JwtClaims,
+    key: &str,
+    alg: JwtAlgorithm,
+) -> anyhow::Result<String> {
+    let jwt = jsonwebtoken::encode(&Header::new(alg.into()), claims, &encoding_key(key, alg)?)?;
 
     Ok(jwt)
 }
 
+/// Load the signing key of `alg`, key files may be PEM, DER or anything `text sign` reads
+fn encoding_key(key: &str, alg: JwtAlgorithm) -> anyhow::Result<EncodingKey> {
+    if alg == JwtAlgorithm::Hs256 {
+        return Ok(EncodingKey::from_secret(key.as_ref()));
+    }
+    let data = read_secret_key_file(key)?;
+    let key = match alg {
+        JwtAlgorithm::Hs256 => unreachable!("HS256 keys are secrets, not files"),
+        // PKCS#1 or PKCS#8, PEM or DER
+        JwtAlgorithm::Rs256 if is_pem(&data) => EncodingKey::from_rsa_pem(&data)?,
+        JwtAlgorithm::Rs256 => EncodingKey::from_rsa_der(rsa_private_der(&data)?),
+        JwtAlgorithm::Es256 => {
+            let key = decode_ec_secret_key::<p256::NistP256>(&data)?;
+            EncodingKey::from_ec_der(key.to_pkcs8_der()?.as_bytes())
+        }
+        JwtAlgorithm::EdDsa => {
+            let key = decode_signing_key(&data)?;
+            EncodingKey::from_ed_der(key.to_pkcs8_der()?.as_bytes())
+        }
+    };
+    Ok(key)
+}
+
+/// Load the verifying key of `alg`, public key files may be PEM, DER or anything `text verify` reads
+fn decoding_key(key: &str, alg: JwtAlgorithm) -> anyhow::Result<DecodingKey> {
+    if alg == JwtAlgorithm::Hs256 {
+        return Ok(DecodingKey::from_secret(key.as_ref()));
+    }
+    let data = std::fs::read(key)?;
+    let key = match alg {
+        JwtAlgorithm::Hs256 => unreachable!("HS256 keys are secrets, not files"),
+        // PKCS#1 or SPKI, PEM or DER
+        JwtAlgorithm::Rs256 if is_pem(&data) => DecodingKey::from_rsa_pem(&data)?,
+        JwtAlgorithm::Rs256 => DecodingKey::from_rsa_der(rsa_public_der(&data)?),
+        // ring wants the uncompressed SEC1 point and the raw ed25519 key
+        JwtAlgorithm::Es256 => {
+            let key = decode_ec_public_key::<p256::NistP256>(&data)?;
+            DecodingKey::from_ec_der(key.to_encoded_point(false).as_bytes())
+        }
+        JwtAlgorithm::EdDsa => DecodingKey::from_ed_der(decode_verifying_key(&data)?.as_bytes()),
+    };
+    Ok(key)
+}
+
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+
+/// The PKCS#1 key ring wants for RSA DER, unwrapped from PKCS#8 when needed
+fn rsa_private_der(data: &[u8]) -> anyhow::Result<&[u8]> {
+    match PrivateKeyInfo::try_from(data) {
+        Ok(info) if info.algorithm.oid == RSA_ENCRYPTION => Ok(info.private_key),
+        Ok(info) => Err(anyhow::anyhow!("Not an RSA key: {}", info.algorithm.oid)),
+        Err(_) => Ok(data),
+    }
+}
+
+/// The PKCS#1 key ring wants for RSA DER, unwrapped from SPKI when needed
+fn rsa_public_der(data: &[u8]) -> anyhow::Result<&[u8]> {
+    match SubjectPublicKeyInfoRef::try_from(data) {
+        Ok(info) if info.algorithm.oid == RSA_ENCRYPTION => info
+            .subject_public_key
+            .as_bytes()
+            .ok_or_else(|| anyhow::anyhow!("Invalid RSA public key")),
+        Ok(info) => Err(anyhow::anyhow!("Not an RSA key: {}", info.algorithm.oid)),
+        Err(_) => Ok(data),
+    }
+}
+
+fn is_pem(data: &[u8]) -> bool {
+    data.trim_ascii_start().starts_with(b"-----BEGIN")
+}
+
 /// Parse the expiration seconds from the input string
 /// for example, "14d" means 14 days, "1h" means 1 hour, "30m" means 30 minutes, "3600" means 3600 seconds
 ///
@@ -46,27 +216,249 @@ fn parse_exp_duration(exp: &str) -> anyhow::Result<u64> {
         }
     }
     let num = num.parse::<u64>()?;
-    let exp = match unit.as_str() {
-        "d" => num * 24 * 60 * 60,
-        "h" => num * 60 * 60,
-        "m" => num * 60,
-        "" => num,
+    let unit = match unit.as_str() {
+        "d" => 24 * 60 * 60,
+        "h" => 60 * 60,
+        "m" => 60,
+        "" => 1,
         _ => return Err(anyhow::anyhow!("Invalid expiration time format")),
     };
-    Ok(exp)
+    num.checked_mul(unit).ok_or_else(expiration_too_large)
 }
 
-pub fn process_jwt_verify(token: &str, key: &str) -> anyhow::Result<JwtClaims> {
-    let mut validation = jsonwebtoken::Validation::default();
-    validation.validate_aud = false;
-    let TokenData { claims, .. } = jsonwebtoken::decode::<JwtClaims>(
-        token,
-        &DecodingKey::from_secret(key.as_ref()),
-        &validation,
-    )?;
+/// Timestamp `duration` after `now`
+fn after(now: u64, duration: &str) -> anyhow::Result<u64> {
+    now.checked_add(parse_exp_duration(duration)?)
+        .ok_or_else(expiration_too_large)
+}
+
+fn expiration_too_large() -> anyhow::Error {
+    anyhow::anyhow!("Expiration time is too large")
+}
+
+/// What `jwt verify` accepts besides a valid signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtPolicy {
+    /// Algorithms the token may be signed with, the key is loaded for the one of its header
+    pub algorithms: Vec<JwtAlgorithm>,
+    /// `aud` must be one of these when not empty
+    pub audiences: Vec<String>,
+    /// `iss` must be one of these when not empty
+    pub issuers: Vec<String>,
+    /// Seconds of clock skew tolerated by the `exp` and `nbf` checks
+    pub leeway: u64,
+    /// Claims that must be present, `exp` always is
+    pub required: Vec<String>,
+}
+
+impl JwtPolicy {
+    pub const DEFAULT_LEEWAY: u64 = 60;
+
+    /// Policy accepting tokens signed with `alg` for any audience and issuer
+    pub fn new(alg: JwtAlgorithm) -> Self {
+        Self {
+            algorithms: vec![alg],
+            audiences: Vec::new(),
+            issuers: Vec::new(),
+            leeway: Self::DEFAULT_LEEWAY,
+            required: Vec::new(),
+        }
+    }
+
+    /// The key is an HS256 secret or a key file, never both: with HS256 allowed next to an
+    /// asymmetric algorithm anyone could sign a token using the key file path as the secret
+    fn check_algorithms(&self) -> anyhow::Result<()> {
+        let hmac = self.algorithms.contains(&JwtAlgorithm::Hs256);
+        match hmac
+            && self
+                .algorithms
+                .iter()
+                .any(|alg| *alg != JwtAlgorithm::Hs256)
+        {
+            true => Err(anyhow::anyhow!(
+                "HS256 cannot be allowed together with the asymmetric algorithms"
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn validation(&self, alg: JwtAlgorithm) -> Validation {
+        let mut validation = Validation::new(alg.into());
+        validation.leeway = self.leeway;
+        validation.validate_nbf = true;
+        validation.validate_aud = !self.audiences.is_empty();
+        if !self.audiences.is_empty() {
+            validation.set_audience(&self.audiences);
+            validation.required_spec_claims.insert("aud".to_string());
+        }
+        if !self.issuers.is_empty() {
+            validation.set_issuer(&self.issuers);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+        validation
+    }
+}
+
+/// Verify a token against `policy`, each failed check is reported with its own reason
+pub fn process_jwt_verify(token: &str, key: &str, policy: &JwtPolicy) -> anyhow::Result<JwtClaims> {
+    policy
+        .check_algorithms()
+        .with_failure(VerifyFailure::KeyError)?;
+    let header = jsonwebtoken::decode_header(token)
+        .map_err(|e| VerifyError::new(jwt_failure(e.kind()), format!("Token is invalid: {}", e)))?;
+    let alg = policy
+        .algorithms
+        .iter()
+        .copied()
+        .find(|alg| Algorithm::from(*alg) == header.alg)
+        .ok_or_else(|| {
+            let allowed = policy.algorithms.iter().map(|alg| alg.to_string());
+            VerifyError::new(
+                VerifyFailure::Invalid,
+                format!(
+                    "Token is signed with {:?}, allowed algorithms: {}",
+                    header.alg,
+                    allowed.collect::<Vec<_>>().join(", ")
+                ),
+            )
+        })?;
+    let key = decoding_key(key, alg).with_failure(VerifyFailure::KeyError)?;
+    let TokenData { claims, .. } =
+        jsonwebtoken::decode::<JwtClaims>(token, &key, &policy.validation(alg)).map_err(|e| {
+            VerifyError::new(jwt_failure(e.kind()), jwt_error_message(&e, token, policy))
+        })?;
+    if let Some(name) = policy
+        .required
+        .iter()
+        .find(|name| claims.get(name).is_none())
+    {
+        return Err(VerifyError::new(
+            VerifyFailure::Invalid,
+            format!("Token is missing the required claim {:?}", name),
+        )
+        .into());
+    }
     Ok(claims)
 }
 
+/// Explain why a token failed verification, quoting the offending claim
+fn jwt_error_message(
+    error: &jsonwebtoken::errors::Error,
+    token: &str,
+    policy: &JwtPolicy,
+) -> String {
+    // only reached once the signature is verified, so the claims can be trusted for the message
+    let claims = unverified_claims(token).unwrap_or_default();
+    let claim = |name: &str| {
+        claims
+            .get(name)
+            .map_or("none".to_string(), Value::to_string)
+    };
+    let time = |name: &str| {
+        claims
+            .time(name)
+            .map_or("an invalid time".to_string(), |t| {
+                t.with_timezone(&Local).to_string()
+            })
+    };
+    match error.kind() {
+        ErrorKind::InvalidSignature => "Signature does not match the key".to_string(),
+        ErrorKind::ExpiredSignature => format!(
+            "Token expired at {} (leeway {}s)",
+            time("exp"),
+            policy.leeway
+        ),
+        ErrorKind::ImmatureSignature => format!(
+            "Token is not valid before {} (leeway {}s)",
+            time("nbf"),
+            policy.leeway
+        ),
+        ErrorKind::InvalidAudience => format!(
+            "Audience {} is not one of the expected {:?}",
+            claim("aud"),
+            policy.audiences
+        ),
+        ErrorKind::InvalidIssuer => format!(
+            "Issuer {} is not one of the expected {:?}",
+            claim("iss"),
+            policy.issuers
+        ),
+        ErrorKind::MissingRequiredClaim(name) => {
+            format!("Token is missing the required claim {:?}", name)
+        }
+        _ => format!("Token is invalid: {}", error),
+    }
+}
+
+/// Header and claims of a token, nothing is verified, not even the signature
+pub fn process_jwt_decode(token: &str) -> anyhow::Result<(Value, JwtClaims)> {
+    let parts = token.trim().split('.').collect::<Vec<_>>();
+    let [header, payload, _] = parts[..] else {
+        return Err(anyhow::anyhow!(
+            "Invalid token: expected 3 '.' separated parts, found {}",
+            parts.len()
+        ));
+    };
+    let decode = |part: &str, name: &str| -> anyhow::Result<Vec<u8>> {
+        BASE64_URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|e| anyhow::anyhow!("Invalid token {}: {}", name, e))
+    };
+    let header = serde_json::from_slice(&decode(header, "header")?)?;
+    let claims = JwtClaims::from_reader(decode(payload, "payload")?.as_slice())?;
+    Ok((header, claims))
+}
+
+/// Coarse human readable duration such as `3h 20m`, at most two units are shown
+pub fn format_duration(secs: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [(24 * 60 * 60, "d"), (60 * 60, "h"), (60, "m"), (1, "s")];
+    let parts = UNITS
+        .iter()
+        .scan(secs, |rest, &(unit, name)| {
+            let n = *rest / unit;
+            *rest %= unit;
+            Some((n, name))
+        })
+        .skip_while(|(n, _)| *n == 0)
+        .take(2)
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, name)| format!("{}{}", n, name))
+        .collect::<Vec<_>>();
+    match parts.is_empty() {
+        true => "0s".to_string(),
+        false => parts.join(" "),
+    }
+}
+
+/// Claims of a token without checking its signature or any claim
+fn unverified_claims(token: &str) -> Option<JwtClaims> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+    let key = DecodingKey::from_secret(&[]);
+    jsonwebtoken::decode::<JwtClaims>(token, &key, &validation)
+        .ok()
+        .map(|data| data.claims)
+}
+
+/// Map a jsonwebtoken error to the verify failure reported by `jwt verify`
+fn jwt_failure(kind: &ErrorKind) -> VerifyFailure {
+    match kind {
+        ErrorKind::InvalidToken
+        | ErrorKind::Base64(_)
+        | ErrorKind::Json(_)
+        | ErrorKind::Utf8(_) => VerifyFailure::MalformedInput,
+        ErrorKind::InvalidKeyFormat
+        | ErrorKind::InvalidRsaKey(_)
+        | ErrorKind::InvalidEcdsaKey
+        | ErrorKind::RsaFailedSigning
+        | ErrorKind::MissingAlgorithm => VerifyFailure::KeyError,
+        _ => VerifyFailure::Invalid,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,11 +470,15 @@ mod tests {
         let iss = jsonwebtoken::get_current_timestamp();
         let exp = "14d";
         let key = "key";
-        let jwt = process_jwt_sign(aud, sub, exp, key).unwrap();
-        let claims = process_jwt_verify(&jwt, key).unwrap();
-        assert_eq!(claims.aud, aud);
-        assert_eq!(claims.sub, sub);
-        assert_eq!(claims.exp, iss + parse_exp_duration(exp).unwrap());
+        let claims = JwtClaims::new(aud, sub, exp).unwrap();
+        let jwt = process_jwt_sign(&claims, key, JwtAlgorithm::Hs256).unwrap();
+        let claims = process_jwt_verify(&jwt, key, &JwtPolicy::new(JwtAlgorithm::Hs256)).unwrap();
+        assert_eq!(claims.get("aud"), Some(&Value::from(aud)));
+        assert_eq!(claims.get("sub"), Some(&Value::from(sub)));
+        assert_eq!(
+            claims.get("exp"),
+            Some(&Value::from(iss + parse_exp_duration(exp).unwrap()))
+        );
     }
 
     #[test]
@@ -91,9 +487,203 @@ mod tests {
         let sub = "acme";
         let exp = "14d";
         let key = "key";
-        let jwt = process_jwt_sign(aud, sub, exp, key).unwrap();
-        assert!(process_jwt_verify(&jwt, "wrong_key").is_err());
-        assert!(process_jwt_verify("wrong_token", key).is_err());
+        let claims = JwtClaims::new(aud, sub, exp).unwrap();
+        let jwt = process_jwt_sign(&claims, key, JwtAlgorithm::Hs256).unwrap();
+        let failure = |token: &str, key: &str| {
+            let err =
+                process_jwt_verify(token, key, &JwtPolicy::new(JwtAlgorithm::Hs256)).unwrap_err();
+            err.downcast_ref::<VerifyError>().unwrap().failure
+        };
+        assert_eq!(failure(&jwt, "wrong_key"), VerifyFailure::Invalid);
+        assert_eq!(failure("wrong_token", key), VerifyFailure::MalformedInput);
+    }
+
+    #[test]
+    fn test_process_jwt_asymmetric_algorithms() -> anyhow::Result<()> {
+        let keys = [
+            (
+                JwtAlgorithm::Rs256,
+                "fixtures/rsa_sk.pem",
+                "fixtures/rsa_pk.pem",
+            ),
+            (
+                JwtAlgorithm::Rs256,
+                "fixtures/rsa_sk.der",
+                "fixtures/rsa_pk.der",
+            ),
+            (
+                JwtAlgorithm::Rs256,
+                "fixtures/rsa_pkcs1_sk.der",
+                "fixtures/rsa_pkcs1_pk.der",
+            ),
+            (
+                JwtAlgorithm::Es256,
+                "fixtures/p256_sk.pem",
+                "fixtures/p256_pk.pem",
+            ),
+            (
+                JwtAlgorithm::EdDsa,
+                "fixtures/ed25519_sk.pem",
+                "fixtures/ed25519_pk.pem",
+            ),
+            (
+                JwtAlgorithm::EdDsa,
+                "fixtures/secret.key",
+                "fixtures/public.key",
+            ),
+        ];
+        let claims = JwtClaims::new("device1", "acme", "1h")?;
+        for (alg, secret, public) in keys {
+            let jwt = process_jwt_sign(&claims, secret, alg)?;
+            assert_eq!(
+                process_jwt_verify(&jwt, public, &JwtPolicy::new(alg))?,
+                claims
+            );
+        }
+
+        let jwt = process_jwt_sign(&claims, "fixtures/secret.key", JwtAlgorithm::EdDsa)?;
+        let err = process_jwt_verify(
+            &jwt,
+            "fixtures/p256_pk.pem",
+            &JwtPolicy::new(JwtAlgorithm::Es256),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<VerifyError>().unwrap().failure,
+            VerifyFailure::Invalid
+        );
+        let err = process_jwt_verify(
+            &jwt,
+            "fixtures/p256_pk.pem",
+            &JwtPolicy::new(JwtAlgorithm::EdDsa),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<VerifyError>().unwrap().failure,
+            VerifyFailure::KeyError
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_jwt_custom_claims() -> anyhow::Result<()> {
+        let mut claims = JwtClaims::from_reader(r#"{"role": "admin", "sub": "file"}"#.as_bytes())?;
+        for claim in [
+            "level=3",
+            "tags=[\"a\",\"b\"]",
+            "name=alice",
+            "quoted=\"3\"",
+        ] {
+            let (name, value) = parse_claim(claim)?;
+            claims.insert(name, value);
+        }
+        claims.insert("jti", generate_jti());
+        claims.set_validity("1h")?;
+        claims.set_not_before("0")?;
+
+        let jwt = process_jwt_sign(&claims, "key", JwtAlgorithm::Hs256)?;
+        let verified = process_jwt_verify(&jwt, "key", &JwtPolicy::new(JwtAlgorithm::Hs256))?;
+        assert_eq!(verified, claims);
+        assert_eq!(verified.get("level"), Some(&Value::from(3)));
+        assert_eq!(verified.get("tags"), Some(&Value::from(vec!["a", "b"])));
+        assert_eq!(verified.get("name"), Some(&Value::from("alice")));
+        assert_eq!(verified.get("quoted"), Some(&Value::from("3")));
+        assert_eq!(verified.get("sub"), Some(&Value::from("file")));
+
+        claims.set_not_before("1h")?;
+        let jwt = process_jwt_sign(&claims, "key", JwtAlgorithm::Hs256)?;
+        assert!(process_jwt_verify(&jwt, "key", &JwtPolicy::new(JwtAlgorithm::Hs256)).is_err());
+
+        assert!(parse_claim("novalue").is_err());
+        assert!(parse_claim("=1").is_err());
+        assert!(parse_claim("aud=other").is_err());
+        assert!(parse_claim("iat=0").is_err());
+        assert!(JwtClaims::from_reader("[1]".as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_jwt_verify_policy() -> anyhow::Result<()> {
+        let mut claims = JwtClaims::new("device1", "acme", "1h")?;
+        claims.insert("iss", "auth");
+        let jwt = process_jwt_sign(&claims, "key", JwtAlgorithm::Hs256)?;
+        let message = |policy: &JwtPolicy| {
+            process_jwt_verify(&jwt, "key", policy)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let mut policy = JwtPolicy::new(JwtAlgorithm::Hs256);
+        policy.audiences = vec!["other".to_string(), "device1".to_string()];
+        policy.issuers = vec!["auth".to_string()];
+        policy.required = vec!["sub".to_string()];
+        assert_eq!(process_jwt_verify(&jwt, "key", &policy)?, claims);
+
+        let mut wrong = policy.clone();
+        wrong.audiences = vec!["other".to_string()];
+        assert!(message(&wrong).starts_with("Audience \"device1\" is not one of"));
+        let mut wrong = policy.clone();
+        wrong.issuers = vec!["other".to_string()];
+        assert!(message(&wrong).starts_with("Issuer \"auth\" is not one of"));
+        let mut wrong = policy.clone();
+        wrong.required = vec!["jti".to_string()];
+        assert_eq!(
+            message(&wrong),
+            "Token is missing the required claim \"jti\""
+        );
+        let mut wrong = policy.clone();
+        wrong.algorithms = vec![JwtAlgorithm::Rs256, JwtAlgorithm::EdDsa];
+        assert_eq!(
+            message(&wrong),
+            "Token is signed with HS256, allowed algorithms: RS256, EdDSA"
+        );
+
+        // a token "signed" with the public key file path as the HS256 secret
+        let forged = process_jwt_sign(&claims, "fixtures/rsa_pk.pem", JwtAlgorithm::Hs256)?;
+        for (algorithms, failure) in [
+            (vec![JwtAlgorithm::Rs256], VerifyFailure::Invalid),
+            (
+                vec![JwtAlgorithm::Rs256, JwtAlgorithm::Hs256],
+                VerifyFailure::KeyError,
+            ),
+        ] {
+            let policy = JwtPolicy {
+                algorithms,
+                ..JwtPolicy::new(JwtAlgorithm::Rs256)
+            };
+            let err = process_jwt_verify(&forged, "fixtures/rsa_pk.pem", &policy).unwrap_err();
+            assert_eq!(err.downcast_ref::<VerifyError>().unwrap().failure, failure);
+        }
+
+        claims.insert("exp", jsonwebtoken::get_current_timestamp() - 30);
+        let jwt = process_jwt_sign(&claims, "key", JwtAlgorithm::Hs256)?;
+        assert!(process_jwt_verify(&jwt, "key", &policy).is_ok());
+        policy.leeway = 0;
+        let err = process_jwt_verify(&jwt, "key", &policy).unwrap_err();
+        assert!(err.to_string().starts_with("Token expired at"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_jwt_decode() -> anyhow::Result<()> {
+        let claims = JwtClaims::new("device1", "acme", "1h")?;
+        let jwt = process_jwt_sign(&claims, "fixtures/secret.key", JwtAlgorithm::EdDsa)?;
+        let (header, decoded) = process_jwt_decode(&jwt)?;
+        assert_eq!(header["alg"], "EdDSA");
+        assert_eq!(decoded, claims);
+
+        assert!(process_jwt_decode("a.b").is_err());
+        assert!(process_jwt_decode("!!.e30.sig").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(3 * 60 * 60), "3h");
+        assert_eq!(format_duration(3 * 60 * 60 + 20 * 60 + 5), "3h 20m");
+        assert_eq!(format_duration(2 * 24 * 60 * 60 + 30), "2d");
     }
 
     #[test]
@@ -110,5 +700,10 @@ mod tests {
         assert!(parse_exp_duration("x14").is_err());
         assert!(parse_exp_duration("abc").is_err());
         assert!(parse_exp_duration("12x12d").is_err());
+        assert!(parse_exp_duration("999999999999999d").is_err());
+        assert!(after(1, &u64::MAX.to_string()).is_err());
+        assert!(JwtClaims::default()
+            .set_not_before("999999999999999d")
+            .is_err());
     }
 }
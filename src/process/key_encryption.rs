@@ -0,0 +1,230 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::path::Path;
+
+/// Environment variable read for the passphrase before falling back to a prompt
+pub const PASSPHRASE_ENV: &str = "RCLI_KEY_PASSPHRASE";
+
+const PEM_HEADER: &str = "-----BEGIN RCLI ENCRYPTED KEY-----";
+const PEM_FOOTER: &str = "-----END RCLI ENCRYPTED KEY-----";
+const MAGIC: &[u8; 8] = b"RCLIKEY1";
+const KDF_ARGON2ID: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// Encrypt a secret key file content with a passphrase.
+///
+/// The key is derived with Argon2id and the content sealed with XChaCha20-Poly1305,
+/// the KDF parameters, salt and nonce are stored in a header that is also authenticated.
+pub fn encrypt_key(plain: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    encrypt_key_with_params(plain, passphrase, Params::default())
+}
+
+fn encrypt_key_with_params(plain: &[u8], passphrase: &[u8], params: Params) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut blob = Vec::with_capacity(HEADER_LEN + plain.len() + 16);
+    blob.extend_from_slice(MAGIC);
+    blob.push(KDF_ARGON2ID);
+    blob.extend_from_slice(&params.m_cost().to_be_bytes());
+    blob.extend_from_slice(&params.t_cost().to_be_bytes());
+    blob.extend_from_slice(&params.p_cost().to_be_bytes());
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &salt, params)?.into());
+    let sealed = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plain,
+                aad: &blob,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt key"))?;
+    blob.extend_from_slice(&sealed);
+
+    let body = STANDARD.encode(&blob);
+    let mut pem = String::from(PEM_HEADER);
+    for line in body.as_bytes().chunks(64) {
+        pem.push('\n');
+        pem.push_str(std::str::from_utf8(line)?);
+    }
+    pem.push('\n');
+    pem.push_str(PEM_FOOTER);
+    pem.push('\n');
+    Ok(pem.into_bytes())
+}
+
+/// Decrypt a key produced by [`encrypt_key`], a wrong passphrase fails authentication
+pub fn decrypt_key(data: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(data)?.trim();
+    let body = text
+        .strip_prefix(PEM_HEADER)
+        .and_then(|s| s.strip_suffix(PEM_FOOTER))
+        .ok_or_else(|| anyhow::anyhow!("Invalid encrypted key armor"))?;
+    let body = body.split_ascii_whitespace().collect::<Vec<_>>().concat();
+    let blob = STANDARD.decode(body)?;
+
+    if blob.len() < HEADER_LEN || &blob[..MAGIC.len()] != MAGIC {
+        return Err(anyhow::anyhow!("Invalid encrypted key header"));
+    }
+    let (header, sealed) = blob.split_at(HEADER_LEN);
+    if header[MAGIC.len()] != KDF_ARGON2ID {
+        return Err(anyhow::anyhow!("Unsupported key derivation function"));
+    }
+    let param = |i: usize| {
+        let start = MAGIC.len() + 1 + i * 4;
+        u32::from_be_bytes(header[start..start + 4].try_into().unwrap())
+    };
+    let params = Params::new(param(0), param(1), param(2), None)
+        .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+    let salt = &header[HEADER_LEN - NONCE_LEN - SALT_LEN..HEADER_LEN - NONCE_LEN];
+    let nonce = &header[HEADER_LEN - NONCE_LEN..];
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, salt, params)?.into());
+    cipher
+        .decrypt(
+            nonce.into(),
+            Payload {
+                msg: sealed,
+                aad: header,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Wrong passphrase or corrupted key"))
+}
+
+pub fn is_encrypted_key(data: &[u8]) -> bool {
+    data.trim_ascii_start().starts_with(PEM_HEADER.as_bytes())
+}
+
+/// Read a secret key file, decrypting it if it is passphrase protected.
+///
+/// The passphrase is taken from `RCLI_KEY_PASSPHRASE` or prompted for on the terminal.
+pub fn read_secret_key_file(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)?;
+    if !is_encrypted_key(&data) {
+        return Ok(data);
+    }
+    decrypt_key(&data, read_passphrase(path)?.as_bytes())
+}
+
+/// Passphrase of the key at `path`, from `RCLI_KEY_PASSPHRASE` or prompted for on the terminal
+pub fn read_passphrase(path: &Path) -> Result<String> {
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => Ok(rpassword::prompt_password(format!(
+            "Passphrase for {:?}: ",
+            path
+        ))?),
+    }
+}
+
+/// Ask for a new passphrase, from `RCLI_KEY_PASSPHRASE` or twice on the terminal
+pub fn read_new_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return check_new_passphrase(passphrase);
+    }
+    let passphrase = check_new_passphrase(rpassword::prompt_password("New passphrase: ")?)?;
+    if rpassword::prompt_password("Confirm passphrase: ")? != passphrase {
+        return Err(anyhow::anyhow!("Passphrases do not match"));
+    }
+    Ok(passphrase)
+}
+
+fn check_new_passphrase(passphrase: String) -> Result<String> {
+    match passphrase.is_empty() {
+        true => Err(anyhow::anyhow!("Passphrase must not be empty")),
+        false => Ok(passphrase),
+    }
+}
+
+/// Write a secret key file that is only readable by its owner
+pub fn write_secret_key_file(path: impl AsRef<Path>, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path.as_ref())?;
+        // mode only applies to new files, tighten existing ones as well
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(data)?;
+    }
+    #[cfg(not(unix))]
+    options.open(path.as_ref())?.write_all(data)?;
+    Ok(())
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8], params: Params) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_params() -> Params {
+        Params::new(64, 2, 1, None).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_key() {
+        let plain = b"super secret key material";
+        let encrypted = encrypt_key_with_params(plain, b"passphrase", test_params()).unwrap();
+        assert!(is_encrypted_key(&encrypted));
+        assert!(!is_encrypted_key(plain));
+        assert_eq!(decrypt_key(&encrypted, b"passphrase").unwrap(), plain);
+        assert!(decrypt_key(&encrypted, b"wrong").is_err());
+    }
+
+    #[test]
+    fn test_tampered_header_should_fail() {
+        let encrypted = encrypt_key_with_params(b"key", b"pass", test_params()).unwrap();
+        let text = String::from_utf8(encrypted).unwrap();
+        let body = text
+            .lines()
+            .filter(|l| !l.starts_with("-----"))
+            .collect::<String>();
+        let mut blob = STANDARD.decode(body).unwrap();
+        // Bump the time cost, the header is authenticated so decryption must fail
+        blob[MAGIC.len() + 8] ^= 1;
+        let tampered = format!("{}\n{}\n{}", PEM_HEADER, STANDARD.encode(blob), PEM_FOOTER);
+        assert!(decrypt_key(tampered.as_bytes(), b"pass").is_err());
+    }
+
+    #[test]
+    fn test_empty_new_passphrase_should_fail() {
+        assert!(check_new_passphrase(String::new()).is_err());
+        assert_eq!(check_new_passphrase("pass".to_string()).unwrap(), "pass");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_secret_key_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("rcli-key-{}", std::process::id()));
+        write_secret_key_file(&path, b"secret").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::cli::text::{KeyFormat, TextSignFormat};
+use crate::process::key_encryption::write_secret_key_file;
+use crate::process::text::{key_file_fingerprint, KEY_ID_LEN};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Environment variable overriding the keyring directory, `~/.rcli/keyring` by default
+pub const KEYRING_ENV: &str = "RCLI_KEYRING";
+
+const META_FILE: &str = "meta.json";
+const SECRET_FILE: &str = "secret.key";
+const PUBLIC_FILE: &str = "public.key";
+/// Shortest key id prefix accepted when looking a key up
+const MIN_QUERY_LEN: usize = 4;
+
+/// Metadata of a keyring key, stored next to the key files in a directory named after its key id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyEntry {
+    pub name: String,
+    pub algorithm: String,
+    /// BLAKE3 fingerprint of the public key, hex encoded
+    pub fingerprint: String,
+    /// RFC 3339 import time
+    pub created: String,
+    pub has_secret: bool,
+    pub has_public: bool,
+}
+
+impl KeyEntry {
+    pub fn key_id(&self) -> &str {
+        &self.fingerprint[..KEY_ID_LEN]
+    }
+
+    pub fn format(&self) -> Result<KeyFormat> {
+        KeyFormat::from_str(&self.algorithm)
+            .map_err(|_| anyhow::anyhow!("Unknown key algorithm: {}", self.algorithm))
+    }
+}
+
+/// Directory of keys, each key lives in `<keyring>/<key id>/`
+pub struct Keyring {
+    dir: PathBuf,
+}
+
+impl Keyring {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Open the keyring named by `RCLI_KEYRING`, or the one in the home directory
+    pub fn open_default() -> Result<Self> {
+        if let Some(dir) = std::env::var_os(KEYRING_ENV) {
+            return Ok(Self::new(dir));
+        }
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .ok_or_else(|| anyhow::anyhow!("No home directory, set {}", KEYRING_ENV))?;
+        Ok(Self::new(Path::new(&home).join(".rcli").join("keyring")))
+    }
+
+    /// All keys sorted by name
+    pub fn list(&self) -> Result<Vec<KeyEntry>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for dir in std::fs::read_dir(&self.dir)? {
+            let dir = dir?.path();
+            let meta = dir.join(META_FILE);
+            if meta.is_file() {
+                let entry = read_entry(&meta).map_err(|e| {
+                    anyhow::anyhow!("Invalid keyring entry {}: {:#}", dir.display(), e)
+                })?;
+                entries.push(entry);
+            }
+        }
+        entries.sort_by(|a: &KeyEntry, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    /// Copy a secret and/or public key file into the keyring, both halves must be of the same key
+    pub fn import(
+        &self,
+        name: &str,
+        format: KeyFormat,
+        secret: Option<&str>,
+        public: Option<&str>,
+    ) -> Result<KeyEntry> {
+        let fingerprints = [(secret, true), (public, false)]
+            .into_iter()
+            .filter_map(|(file, is_secret)| {
+                file.map(|file| key_file_fingerprint(file, format, is_secret))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let fingerprint = match fingerprints[..] {
+            [] => return Err(anyhow::anyhow!("A secret or a public key file is required")),
+            [ref a, ref b] if a != b => {
+                return Err(anyhow::anyhow!(
+                    "The secret and public key files are not a key pair"
+                ))
+            }
+            [ref fingerprint, ..] => fingerprint.clone(),
+        };
+
+        let existing = self.list()?;
+        if let Some(entry) = existing.iter().find(|e| e.fingerprint == fingerprint) {
+            return Err(anyhow::anyhow!(
+                "Key {} is already in the keyring as {:?}",
+                entry.key_id(),
+                entry.name
+            ));
+        }
+        if existing.iter().any(|e| e.name == name) {
+            return Err(anyhow::anyhow!("A key named {:?} already exists", name));
+        }
+
+        let entry = KeyEntry {
+            name: name.to_string(),
+            algorithm: format.to_string(),
+            fingerprint,
+            created: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            has_secret: secret.is_some(),
+            has_public: public.is_some(),
+        };
+        let dir = self.dir.join(entry.key_id());
+        std::fs::create_dir_all(&dir)?;
+        if let Some(secret) = secret {
+            // encrypted keys are copied as they are and stay encrypted
+            write_secret_key_file(dir.join(SECRET_FILE), &std::fs::read(secret)?)?;
+        }
+        if let Some(public) = public {
+            std::fs::copy(public, dir.join(PUBLIC_FILE))?;
+        }
+        std::fs::write(dir.join(META_FILE), serde_json::to_vec_pretty(&entry)?)?;
+        Ok(entry)
+    }
+
+    /// Find a key by exact name or by a prefix of its fingerprint
+    pub fn find(&self, query: &str) -> Result<KeyEntry> {
+        let entries = self.list()?;
+        if let Some(entry) = entries.iter().find(|e| e.name == query) {
+            return Ok(entry.clone());
+        }
+        let query = query.to_ascii_lowercase();
+        let mut matches = entries
+            .into_iter()
+            .filter(|e| query.len() >= MIN_QUERY_LEN && e.fingerprint.starts_with(query.as_str()));
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (Some(_), Some(_)) => Err(anyhow::anyhow!(
+                "Key id {} is ambiguous, give more characters",
+                query
+            )),
+            (None, _) => Err(anyhow::anyhow!("No key {:?} in the keyring", query)),
+        }
+    }
+
+    pub fn secret_path(&self, entry: &KeyEntry) -> Result<PathBuf> {
+        match entry.has_secret {
+            true => Ok(self.dir.join(entry.key_id()).join(SECRET_FILE)),
+            false => Err(anyhow::anyhow!(
+                "Key {} has no secret key in the keyring",
+                entry.key_id()
+            )),
+        }
+    }
+
+    /// Public key file, symmetric keys are their own public half
+    pub fn public_path(&self, entry: &KeyEntry) -> Result<PathBuf> {
+        match entry.has_public {
+            true => Ok(self.dir.join(entry.key_id()).join(PUBLIC_FILE)),
+            false if entry.format()?.is_symmetric() => self.secret_path(entry),
+            false => Err(anyhow::anyhow!(
+                "Key {} has no public key in the keyring",
+                entry.key_id()
+            )),
+        }
+    }
+
+    /// Resolve `query` to the key file to sign (`secret`) or verify with, checking it fits `format`
+    pub fn resolve(
+        &self,
+        query: &str,
+        format: Option<TextSignFormat>,
+        secret: bool,
+    ) -> Result<(String, TextSignFormat)> {
+        let entry = self.find(query)?;
+        let key_format = entry
+            .format()?
+            .sign_format()
+            .map_err(|e| anyhow::anyhow!("Key {}: {}", entry.key_id(), e))?;
+        if let Some(format) = format.filter(|f| *f != key_format) {
+            return Err(anyhow::anyhow!(
+                "Key {} is a {} key, not {}",
+                entry.key_id(),
+                key_format,
+                format
+            ));
+        }
+        let path = match secret {
+            true => self.secret_path(&entry)?,
+            false => self.public_path(&entry)?,
+        };
+        Ok((path.to_string_lossy().into_owned(), key_format))
+    }
+
+    pub fn delete(&self, query: &str) -> Result<KeyEntry> {
+        let entry = self.find(query)?;
+        std::fs::remove_dir_all(self.dir.join(entry.key_id()))?;
+        Ok(entry)
+    }
+}
+
+/// Read a `meta.json`, checking the fingerprint the key id is cut from
+fn read_entry(meta: &Path) -> Result<KeyEntry> {
+    let entry: KeyEntry = serde_json::from_slice(&std::fs::read(meta)?)?;
+    let fingerprint = entry.fingerprint.as_bytes();
+    if fingerprint.len() != 2 * blake3::OUT_LEN || !fingerprint.iter().all(u8::is_ascii_hexdigit) {
+        return Err(anyhow::anyhow!(
+            "fingerprint must be {} hex characters",
+            2 * blake3::OUT_LEN
+        ));
+    }
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_import_find_delete() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-keyring-{}", std::process::id()));
+        let keyring = Keyring::new(&dir);
+        let entry = keyring.import(
+            "release",
+            KeyFormat::Sign(TextSignFormat::Ed25519),
+            Some("fixtures/secret.key"),
+            Some("fixtures/public.key"),
+        )?;
+        keyring.import(
+            "mac",
+            KeyFormat::Sign(TextSignFormat::Blake3),
+            Some("fixtures/blake3.key"),
+            None,
+        )?;
+        keyring.import(
+            "box",
+            KeyFormat::X25519,
+            Some("fixtures/x25519_sk.pem"),
+            Some("fixtures/x25519_pk.pem"),
+        )?;
+        assert!(keyring
+            .import(
+                "again",
+                KeyFormat::Sign(TextSignFormat::Ed25519),
+                None,
+                Some("fixtures/public.key")
+            )
+            .is_err());
+        assert!(keyring
+            .import(
+                "pair",
+                KeyFormat::Sign(TextSignFormat::Ed25519),
+                Some("fixtures/secret.key"),
+                Some("fixtures/ed25519_pk.pem")
+            )
+            .is_err());
+
+        let names = keyring
+            .list()?
+            .into_iter()
+            .map(|e| e.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["box", "mac", "release"]);
+        assert_eq!(keyring.find("release")?, entry);
+        assert_eq!(keyring.find(&entry.key_id()[..6])?, entry);
+        assert!(keyring.find("abc").is_err());
+
+        let (path, format) = keyring.resolve("release", None, false)?;
+        assert_eq!(format, TextSignFormat::Ed25519);
+        assert_eq!(std::fs::read(path)?, std::fs::read("fixtures/public.key")?);
+        assert!(keyring
+            .resolve("release", Some(TextSignFormat::Blake3), true)
+            .is_err());
+        assert!(keyring.resolve("box", None, true).is_err());
+        let (path, _) = keyring.resolve("mac", None, false)?;
+        assert!(path.ends_with(SECRET_FILE));
+
+        keyring.delete("release")?;
+        let remaining = keyring.list()?;
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(remaining.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyring_corrupt_entry() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-keyring-bad-{}", std::process::id()));
+        let keyring = Keyring::new(&dir);
+        keyring.import(
+            "mac",
+            KeyFormat::Sign(TextSignFormat::Blake3),
+            Some("fixtures/blake3.key"),
+            None,
+        )?;
+        let entry = keyring.find("mac")?;
+        let bad = dir.join(entry.key_id());
+        let edited = KeyEntry {
+            fingerprint: "abc".to_string(),
+            ..entry
+        };
+        std::fs::write(bad.join(META_FILE), serde_json::to_vec(&edited)?)?;
+
+        let list = keyring.list();
+        let find = keyring.find("mac");
+        std::fs::remove_dir_all(&dir)?;
+        let err = list.unwrap_err().to_string();
+        assert!(err.contains(&bad.display().to_string()), "{}", err);
+        assert!(find.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use crate::cli::codec::CodecFormat;
+use crate::cli::hash::HashAlgorithm;
+use crate::process::codec::encode_bytes;
+use crate::process::hash::hash_files;
+use crate::process::text::{Ed25519Signer, Ed25519Verifier, TextSigner, TextVerifier};
+use anyhow::Result;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Signed listing of every file of a directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignedManifest {
+    pub manifest: Manifest,
+    /// Ed25519 signature over the JSON serialization of `manifest`, base64url encoded
+    pub signature: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub algorithm: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the signed directory, always `/` separated
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Difference between a directory and its manifest, paths are sorted
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DirDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+impl DirDiff {
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Hash every regular file under `dir` with BLAKE3 and sign the resulting manifest
+///
+/// Symlinks and special files are skipped, `exclude` (usually the manifest itself) is left out.
+pub fn process_sign_dir(dir: &Path, key: &str, exclude: Option<&Path>) -> Result<SignedManifest> {
+    let signer = Ed25519Signer::from_file(key)?;
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        algorithm: HashAlgorithm::Blake3.to_string(),
+        files: scan_dir(dir, exclude)?,
+    };
+    let sig = signer.sign(Cursor::new(serde_json::to_vec(&manifest)?))?;
+    Ok(SignedManifest {
+        manifest,
+        signature: BASE64_URL_SAFE_NO_PAD.encode(sig),
+    })
+}
+
+/// Check the manifest signature, then compare the manifest with the current content of `dir`
+pub fn process_verify_dir(
+    dir: &Path,
+    key: &str,
+    signed: &SignedManifest,
+    exclude: Option<&Path>,
+) -> Result<DirDiff> {
+    let verifier = Ed25519Verifier::from_file(key)?;
+    let sig = BASE64_URL_SAFE_NO_PAD.decode(signed.signature.trim())?;
+    let data = serde_json::to_vec(&signed.manifest)?;
+    if !verifier.verify(Cursor::new(data), &sig)? {
+        return Err(anyhow::anyhow!("Manifest signature is invalid"));
+    }
+    if signed.manifest.version != MANIFEST_VERSION
+        || signed.manifest.algorithm != HashAlgorithm::Blake3.to_string()
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported manifest version {} ({})",
+            signed.manifest.version,
+            signed.manifest.algorithm
+        ));
+    }
+
+    let mut expected = signed
+        .manifest
+        .files
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry))
+        .collect::<BTreeMap<_, _>>();
+    let mut diff = DirDiff::default();
+    for entry in scan_dir(dir, exclude)? {
+        match expected.remove(entry.path.as_str()) {
+            None => diff.added.push(entry.path),
+            Some(old) if *old == entry => diff.unchanged.push(entry.path),
+            Some(_) => diff.modified.push(entry.path),
+        }
+    }
+    diff.removed = expected.into_keys().map(String::from).collect();
+    Ok(diff)
+}
+
+/// List the regular files of `dir` with their size and BLAKE3 hash, sorted by path
+fn scan_dir(dir: &Path, exclude: Option<&Path>) -> Result<Vec<ManifestEntry>> {
+    let exclude = exclude.and_then(|path| path.canonicalize().ok());
+    let mut files = Vec::new();
+    walk(dir, &mut files)?;
+    files.retain(|path| exclude.is_none() || path.canonicalize().ok() != exclude);
+
+    let paths = files
+        .iter()
+        .map(|path| utf8_path(path).map(String::from))
+        .collect::<Result<Vec<_>>>()?;
+    let hashes = hash_files(&paths, HashAlgorithm::Blake3, None);
+    let mut manifest = files
+        .iter()
+        .zip(hashes)
+        .map(|(path, hash)| {
+            let relative = path
+                .strip_prefix(dir)?
+                .components()
+                .map(|c| utf8_path(Path::new(c.as_os_str())))
+                .collect::<Result<Vec<_>>>()?
+                .join("/");
+            Ok(ManifestEntry {
+                path: relative,
+                size: std::fs::metadata(path)?.len(),
+                hash: String::from_utf8(encode_bytes(&hash?, CodecFormat::Hex))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    manifest.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(manifest)
+}
+
+/// Manifest paths are UTF-8 strings, a lossy conversion could make two files share a path
+fn utf8_path(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow::anyhow!("Path is not valid UTF-8: {}", path.display()))
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify_dir() {
+        let dir = std::env::temp_dir().join(format!("rcli-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        std::fs::write(dir.join("sub/b.txt"), "b").unwrap();
+        std::fs::write(dir.join("c.txt"), "c").unwrap();
+
+        let signed = process_sign_dir(&dir, "fixtures/secret.key", None).unwrap();
+        let paths = signed
+            .manifest
+            .files
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["a.txt", "c.txt", "sub/b.txt"]);
+        let diff = process_verify_dir(&dir, "fixtures/public.key", &signed, None).unwrap();
+        assert!(diff.is_clean());
+        assert_eq!(diff.unchanged.len(), 3);
+
+        std::fs::write(dir.join("a.txt"), "changed").unwrap();
+        std::fs::remove_file(dir.join("c.txt")).unwrap();
+        std::fs::write(dir.join("sub/new.txt"), "new").unwrap();
+        let diff = process_verify_dir(&dir, "fixtures/public.key", &signed, None).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            diff,
+            DirDiff {
+                added: vec!["sub/new.txt".to_string()],
+                removed: vec!["c.txt".to_string()],
+                modified: vec!["a.txt".to_string()],
+                unchanged: vec!["sub/b.txt".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_tampered_manifest_should_fail() {
+        let mut signed =
+            process_sign_dir(Path::new("fixtures"), "fixtures/secret.key", None).unwrap();
+        signed.manifest.files[0].size += 1;
+        let result =
+            process_verify_dir(Path::new("fixtures"), "fixtures/public.key", &signed, None);
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_should_fail() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = std::env::temp_dir().join(format!("rcli-manifest-utf8-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(std::ffi::OsStr::from_bytes(b"bad\xff.txt")), "x").unwrap();
+        let result = process_sign_dir(&dir, "fixtures/secret.key", None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+    }
+}
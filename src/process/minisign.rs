@@ -0,0 +1,392 @@
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::io::Read;
+
+/// Length of a minisign key id
+pub const MINISIGN_KEY_ID_LEN: usize = 8;
+
+const SIG_ALG: &[u8; 2] = b"Ed";
+/// Signatures over the BLAKE2b-512 hash of the input, the default since minisign 0.10
+const SIG_ALG_PREHASHED: &[u8; 2] = b"ED";
+const KDF_SCRYPT: &[u8; 2] = b"Sc";
+const KDF_NONE: &[u8; 2] = &[0, 0];
+const CHK_BLAKE2B: &[u8; 2] = b"B2";
+/// scrypt limits minisign encrypts new secret keys with
+const OPSLIMIT: u64 = 33_554_432;
+const MEMLIMIT: u64 = 1_073_741_824;
+const SALT_LEN: usize = 32;
+/// key id, ed25519 secret key (seed and public key) and BLAKE2b-256 checksum
+const KEYNUM_SK_LEN: usize = MINISIGN_KEY_ID_LEN + 64 + 32;
+const SECRET_KEY_LEN: usize = 6 + SALT_LEN + 16 + KEYNUM_SK_LEN;
+const PUBLIC_KEY_LEN: usize = 2 + MINISIGN_KEY_ID_LEN + 32;
+const SIGNATURE_LEN: usize = 2 + MINISIGN_KEY_ID_LEN + 64;
+
+const UNTRUSTED_COMMENT: &str = "untrusted comment: ";
+const TRUSTED_COMMENT: &str = "trusted comment: ";
+
+type Blake2b256 = Blake2b<U32>;
+
+/// A `.minisig` file
+#[derive(Debug, Clone)]
+pub struct MinisignSignature {
+    pub untrusted_comment: String,
+    pub prehashed: bool,
+    pub key_id: [u8; MINISIGN_KEY_ID_LEN],
+    pub signature: Signature,
+    /// Comment covered by the global signature, minisign puts a timestamp and the file name here
+    pub trusted_comment: String,
+    pub global_signature: Signature,
+}
+
+impl MinisignSignature {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(data)?;
+        let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+        let mut next = || {
+            lines
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Truncated minisign signature"))
+        };
+        let untrusted_comment = next()?
+            .strip_prefix(UNTRUSTED_COMMENT)
+            .ok_or_else(|| anyhow::anyhow!("Missing untrusted comment in minisign signature"))?
+            .to_string();
+        let sig = STANDARD.decode(next()?.trim())?;
+        let trusted_comment = next()?
+            .strip_prefix(TRUSTED_COMMENT)
+            .ok_or_else(|| anyhow::anyhow!("Missing trusted comment in minisign signature"))?
+            .to_string();
+        let global_signature = STANDARD.decode(next()?.trim())?;
+        if sig.len() != SIGNATURE_LEN {
+            return Err(anyhow::anyhow!("Invalid minisign signature length"));
+        }
+        let prehashed = match &sig[..2] {
+            alg if alg == SIG_ALG_PREHASHED => true,
+            alg if alg == SIG_ALG => false,
+            _ => return Err(anyhow::anyhow!("Unsupported minisign signature algorithm")),
+        };
+        Ok(Self {
+            untrusted_comment,
+            prehashed,
+            key_id: sig[2..10].try_into()?,
+            signature: Signature::from_bytes(sig[10..].try_into()?),
+            trusted_comment,
+            global_signature: Signature::from_bytes(global_signature.as_slice().try_into()?),
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        let alg = match self.prehashed {
+            true => SIG_ALG_PREHASHED,
+            false => SIG_ALG,
+        };
+        let sig = [alg.as_slice(), &self.key_id, &self.signature.to_bytes()].concat();
+        format!(
+            "{}{}\n{}\n{}{}\n{}\n",
+            UNTRUSTED_COMMENT,
+            self.untrusted_comment,
+            STANDARD.encode(sig),
+            TRUSTED_COMMENT,
+            self.trusted_comment,
+            STANDARD.encode(self.global_signature.to_bytes())
+        )
+    }
+}
+
+/// Whether `data` is a minisign key or signature file rather than a raw, PEM or OpenSSH key
+pub fn is_minisign(data: &[u8]) -> bool {
+    data.trim_ascii_start()
+        .starts_with(UNTRUSTED_COMMENT.trim_end().as_bytes())
+}
+
+/// Key id of keys that were not created by minisign, the start of their BLAKE3 fingerprint
+pub fn minisign_key_id(key: &VerifyingKey) -> [u8; MINISIGN_KEY_ID_LEN] {
+    blake3::hash(key.as_bytes()).as_bytes()[..MINISIGN_KEY_ID_LEN]
+        .try_into()
+        .unwrap()
+}
+
+/// Key id as minisign prints it
+pub fn format_key_id(key_id: &[u8; MINISIGN_KEY_ID_LEN]) -> String {
+    format!("{:016X}", u64::from_le_bytes(*key_id))
+}
+
+/// Sign with the prehashed algorithm, the global signature covers the signature and `trusted_comment`
+pub fn minisign_sign(
+    key: &SigningKey,
+    key_id: [u8; MINISIGN_KEY_ID_LEN],
+    mut reader: impl Read,
+    trusted_comment: &str,
+) -> Result<MinisignSignature> {
+    if trusted_comment.contains(['\n', '\r']) {
+        return Err(anyhow::anyhow!("The trusted comment must be a single line"));
+    }
+    let mut hasher = Blake2b512::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+    let signature = key.sign(&hasher.finalize());
+    let global_signature = key.sign(&[&signature.to_bytes(), trusted_comment.as_bytes()].concat());
+    Ok(MinisignSignature {
+        untrusted_comment: "signature from rcli secret key".to_string(),
+        prehashed: true,
+        key_id,
+        signature,
+        trusted_comment: trusted_comment.to_string(),
+        global_signature,
+    })
+}
+
+/// Check both the signature of the input and the global signature of the trusted comment
+pub fn minisign_verify(
+    key: &VerifyingKey,
+    mut reader: impl Read,
+    sig: &MinisignSignature,
+) -> Result<bool> {
+    let signed = match sig.prehashed {
+        true => {
+            let mut hasher = Blake2b512::new();
+            std::io::copy(&mut reader, &mut hasher)?;
+            hasher.finalize().to_vec()
+        }
+        false => {
+            let mut buffer = Vec::new();
+            reader.read_to_end(&mut buffer)?;
+            buffer
+        }
+    };
+    let global = [&sig.signature.to_bytes(), sig.trusted_comment.as_bytes()].concat();
+    Ok(key.verify(&signed, &sig.signature).is_ok()
+        && key.verify(&global, &sig.global_signature).is_ok())
+}
+
+/// Load a minisign public key file, or the bare base64 line `minisign -P` takes
+pub fn decode_minisign_public_key(
+    data: &[u8],
+) -> Result<([u8; MINISIGN_KEY_ID_LEN], VerifyingKey)> {
+    let key = STANDARD.decode(key_line(data)?)?;
+    if key.len() != PUBLIC_KEY_LEN || &key[..2] != SIG_ALG {
+        return Err(anyhow::anyhow!("Unsupported minisign public key"));
+    }
+    Ok((
+        key[2..10].try_into()?,
+        VerifyingKey::from_bytes(key[10..].try_into()?)?,
+    ))
+}
+
+pub fn encode_minisign_public_key(key_id: [u8; MINISIGN_KEY_ID_LEN], key: &VerifyingKey) -> String {
+    let data = [SIG_ALG.as_slice(), &key_id, key.as_bytes()].concat();
+    format!(
+        "{}minisign public key {}\n{}\n",
+        UNTRUSTED_COMMENT,
+        format_key_id(&key_id),
+        STANDARD.encode(data)
+    )
+}
+
+/// Load a minisign secret key, `passphrase` is only called for encrypted keys
+pub fn decode_minisign_secret_key(
+    data: &[u8],
+    passphrase: impl FnOnce() -> Result<String>,
+) -> Result<([u8; MINISIGN_KEY_ID_LEN], SigningKey)> {
+    let key = STANDARD.decode(key_line(data)?)?;
+    if key.len() != SECRET_KEY_LEN || &key[..2] != SIG_ALG || &key[4..6] != CHK_BLAKE2B {
+        return Err(anyhow::anyhow!("Unsupported minisign secret key"));
+    }
+    let salt = &key[6..6 + SALT_LEN];
+    let opslimit = u64::from_le_bytes(key[38..46].try_into()?);
+    let memlimit = u64::from_le_bytes(key[46..54].try_into()?);
+    let mut keynum_sk = key[54..].to_vec();
+    match &key[2..4] {
+        kdf if kdf == KDF_SCRYPT => {
+            let stream = scrypt_stream(passphrase()?.as_bytes(), salt, opslimit, memlimit)?;
+            keynum_sk.iter_mut().zip(stream).for_each(|(b, k)| *b ^= k);
+        }
+        kdf if kdf == KDF_NONE => {}
+        _ => return Err(anyhow::anyhow!("Unsupported minisign key derivation")),
+    }
+
+    let (key_id, rest) = keynum_sk.split_at(MINISIGN_KEY_ID_LEN);
+    let (secret, checksum) = rest.split_at(64);
+    if Blake2b256::new()
+        .chain_update(SIG_ALG)
+        .chain_update(key_id)
+        .chain_update(secret)
+        .finalize()
+        .as_slice()
+        != checksum
+    {
+        return Err(anyhow::anyhow!(
+            "Wrong passphrase or corrupted minisign key"
+        ));
+    }
+    let signing_key = SigningKey::from_keypair_bytes(secret.try_into()?)?;
+    Ok((key_id.try_into()?, signing_key))
+}
+
+/// Encode a secret key the way `minisign -G` does, unencrypted keys are written like `minisign -G -W`
+pub fn encode_minisign_secret_key(
+    key_id: [u8; MINISIGN_KEY_ID_LEN],
+    key: &SigningKey,
+    passphrase: Option<&str>,
+) -> Result<String> {
+    encode_secret_key_with_limits(key_id, key, passphrase, OPSLIMIT, MEMLIMIT)
+}
+
+fn encode_secret_key_with_limits(
+    key_id: [u8; MINISIGN_KEY_ID_LEN],
+    key: &SigningKey,
+    passphrase: Option<&str>,
+    opslimit: u64,
+    memlimit: u64,
+) -> Result<String> {
+    let secret = key.to_keypair_bytes();
+    let checksum = Blake2b256::new()
+        .chain_update(SIG_ALG)
+        .chain_update(key_id)
+        .chain_update(secret)
+        .finalize();
+    let mut keynum_sk = [key_id.as_slice(), &secret, &checksum].concat();
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = match passphrase {
+        Some(passphrase) => {
+            let stream = scrypt_stream(passphrase.as_bytes(), &salt, opslimit, memlimit)?;
+            keynum_sk.iter_mut().zip(stream).for_each(|(b, k)| *b ^= k);
+            KDF_SCRYPT
+        }
+        None => KDF_NONE,
+    };
+    let data = [
+        SIG_ALG.as_slice(),
+        kdf,
+        CHK_BLAKE2B,
+        &salt,
+        &opslimit.to_le_bytes(),
+        &memlimit.to_le_bytes(),
+        &keynum_sk,
+    ]
+    .concat();
+    let comment = match passphrase {
+        Some(_) => "minisign encrypted secret key",
+        None => "minisign secret key",
+    };
+    Ok(format!(
+        "{}{}\n{}\n",
+        UNTRUSTED_COMMENT,
+        comment,
+        STANDARD.encode(data)
+    ))
+}
+
+/// The base64 line of a key file, after the optional untrusted comment
+fn key_line(data: &[u8]) -> Result<&str> {
+    std::str::from_utf8(data)?
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with(UNTRUSTED_COMMENT.trim_end()))
+        .ok_or_else(|| anyhow::anyhow!("Empty minisign key"))
+}
+
+/// Keystream XORed with the secret key, scrypt parameters are picked from the limits like libsodium does
+fn scrypt_stream(passphrase: &[u8], salt: &[u8], opslimit: u64, memlimit: u64) -> Result<Vec<u8>> {
+    let opslimit = opslimit.max(32768);
+    let r = 8u32;
+    let log_n_for = |max_n: u64| (1..63).find(|n| 1u64 << n > max_n / 2).unwrap_or(63) as u8;
+    let (log_n, p) = if opslimit < memlimit / 32 {
+        (log_n_for(opslimit / (r as u64 * 4)), 1)
+    } else {
+        let log_n = log_n_for(memlimit / (r as u64 * 128));
+        let max_rp = ((opslimit / 4) >> log_n).min(0x3fff_ffff) as u32;
+        (log_n, (max_rp / r).max(1))
+    };
+    let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+        .map_err(|e| anyhow::anyhow!("Invalid minisign scrypt parameters: {}", e))?;
+    let mut stream = vec![0u8; KEYNUM_SK_LEN];
+    scrypt::scrypt(passphrase, salt, &params, &mut stream)
+        .map_err(|e| anyhow::anyhow!("Failed to derive the minisign key: {}", e))?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors of the minisign-verify crate, signatures of "test"
+    const PUBLIC_KEY: &str = "untrusted comment: minisign public key E7620F1842B4E81F
+RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==
+";
+    const PREHASHED_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+    #[test]
+    fn test_verify_minisign_signature() -> Result<()> {
+        let (key_id, key) = decode_minisign_public_key(PUBLIC_KEY.as_bytes())?;
+        assert_eq!(format_key_id(&key_id), "E7620F1842B4E81F");
+        for (sig, prehashed) in [(LEGACY_SIGNATURE, false), (PREHASHED_SIGNATURE, true)] {
+            let sig = MinisignSignature::parse(sig.as_bytes())?;
+            assert_eq!(sig.key_id, key_id);
+            assert_eq!(sig.prehashed, prehashed);
+            assert!(minisign_verify(&key, b"test".as_slice(), &sig)?);
+            assert!(!minisign_verify(&key, b"Test".as_slice(), &sig)?);
+
+            let mut tampered = sig.clone();
+            tampered.trusted_comment.push('!');
+            assert!(!minisign_verify(&key, b"test".as_slice(), &tampered)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_minisign_sign_roundtrip() -> Result<()> {
+        let key = SigningKey::generate(&mut OsRng);
+        let key_id = minisign_key_id(&key.verifying_key());
+        let sig = minisign_sign(&key, key_id, b"hello".as_slice(), "timestamp:0")?;
+        let parsed = MinisignSignature::parse(sig.encode().as_bytes())?;
+        assert_eq!(parsed.trusted_comment, "timestamp:0");
+        assert!(minisign_verify(
+            &key.verifying_key(),
+            b"hello".as_slice(),
+            &parsed
+        )?);
+        assert!(minisign_sign(&key, key_id, b"".as_slice(), "a\nb").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_minisign_key_roundtrip() -> Result<()> {
+        let key = SigningKey::generate(&mut OsRng);
+        let key_id = minisign_key_id(&key.verifying_key());
+        let public = encode_minisign_public_key(key_id, &key.verifying_key());
+        assert_eq!(
+            decode_minisign_public_key(public.as_bytes())?,
+            (key_id, key.verifying_key())
+        );
+
+        let plain = encode_minisign_secret_key(key_id, &key, None)?;
+        let (id, decoded) = decode_minisign_secret_key(plain.as_bytes(), || unreachable!())?;
+        assert_eq!((id, decoded.to_bytes()), (key_id, key.to_bytes()));
+
+        // low limits keep the test fast, the parameter selection is the same
+        let encrypted = encode_secret_key_with_limits(key_id, &key, Some("pass"), 32768, 1 << 20)?;
+        let (_, decoded) =
+            decode_minisign_secret_key(encrypted.as_bytes(), || Ok("pass".to_string()))?;
+        assert_eq!(decoded.to_bytes(), key.to_bytes());
+        assert!(
+            decode_minisign_secret_key(encrypted.as_bytes(), || Ok("wrong".to_string())).is_err()
+        );
+        Ok(())
+    }
+}
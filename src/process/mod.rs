@@ -1,6 +1,17 @@
 pub mod base64;
+pub mod codec;
 pub mod csv;
+pub mod encryption;
+pub mod envelope;
 pub mod gen_pass;
+pub mod hash;
 pub mod http;
 pub mod jwt;
+pub mod key_encryption;
+pub mod key_format;
+pub mod keyring;
+pub mod manifest;
+pub mod minisign;
+pub mod seal;
 pub mod text;
+pub mod verify;
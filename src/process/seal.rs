@@ -0,0 +1,241 @@
+use crate::cli::base64::Base64Format;
+use crate::cli::text::TextCipher;
+use crate::process::base64::{process_decode, LineWrapWriter};
+use crate::process::encryption::{process_decrypt, process_encrypt};
+use crate::process::text::Blake3;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::write::EncoderWriter;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use std::io::{BufRead, BufReader, Read, Write};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+const MAGIC: &[u8; 8] = b"RCLISEAL";
+const VERSION: u8 = 1;
+const KEY_LEN: usize = 32;
+const WRAPPED_KEY_LEN: usize = KEY_LEN + 16;
+const STANZA_LEN: usize = KEY_LEN + WRAPPED_KEY_LEN;
+const MAC_LEN: usize = 32;
+const WRAP_INFO: &[u8] = b"rcli 2024 seal x25519 v1";
+const MAC_CONTEXT: &str = "rcli 2024 seal header mac v1";
+const ARMOR_HEADER: &str = "-----BEGIN RCLI SEALED MESSAGE-----";
+const ARMOR_FOOTER: &str = "-----END RCLI SEALED MESSAGE-----";
+const ARMOR_WIDTH: usize = 64;
+
+/// Encrypt `reader` to one or more X25519 recipients, returns the number of plaintext bytes
+///
+/// A random file key encrypts the payload with the streaming text encryption format. The file key
+/// is wrapped once per recipient with a key agreed between a fresh ephemeral key and the recipient
+/// key, and the whole header is authenticated with a MAC derived from the file key.
+pub fn process_seal(
+    reader: impl Read,
+    mut writer: impl Write,
+    recipients: &[PublicKey],
+    armor: bool,
+) -> Result<u64> {
+    if !armor {
+        return seal(reader, writer, recipients);
+    }
+
+    writeln!(writer, "{}", ARMOR_HEADER)?;
+    let n = {
+        let wrapped = LineWrapWriter::new(&mut writer, ARMOR_WIDTH, b"\n");
+        let mut encoder = EncoderWriter::new(wrapped, &STANDARD);
+        let n = seal(reader, &mut encoder, recipients)?;
+        encoder.finish()?;
+        n
+    };
+    writeln!(writer, "\n{}", ARMOR_FOOTER)?;
+    writer.flush()?;
+    Ok(n)
+}
+
+/// Decrypt a message produced by [`process_seal`] with a recipient secret key, armor is detected
+pub fn process_open(reader: impl Read, writer: impl Write, identity: &StaticSecret) -> Result<u64> {
+    let mut reader = BufReader::new(reader);
+    let armored = reader
+        .fill_buf()?
+        .trim_ascii_start()
+        .starts_with(ARMOR_HEADER.as_bytes());
+    if !armored {
+        return open(reader, writer, identity);
+    }
+
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let body = text
+        .trim()
+        .strip_prefix(ARMOR_HEADER)
+        .and_then(|s| s.strip_suffix(ARMOR_FOOTER))
+        .ok_or_else(|| anyhow::anyhow!("Invalid sealed message armor"))?;
+    let mut data = Vec::new();
+    process_decode(body.as_bytes(), &mut data, Base64Format::Standard, true)?;
+    open(data.as_slice(), writer, identity)
+}
+
+fn seal(reader: impl Read, mut writer: impl Write, recipients: &[PublicKey]) -> Result<u64> {
+    if recipients.is_empty() {
+        return Err(anyhow::anyhow!("At least one recipient is required"));
+    }
+    let count = u16::try_from(recipients.len())
+        .map_err(|_| anyhow::anyhow!("Too many recipients: {}", recipients.len()))?;
+
+    let mut file_key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut file_key);
+
+    let mut header = Vec::with_capacity(MAGIC.len() + 3 + recipients.len() * STANZA_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(VERSION);
+    header.extend_from_slice(&count.to_be_bytes());
+    for recipient in recipients {
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(recipient);
+        if !shared.was_contributory() {
+            return Err(anyhow::anyhow!("Invalid x25519 recipient key"));
+        }
+        let cipher = wrap_cipher(shared.as_bytes(), &ephemeral_public, recipient)?;
+        let wrapped = cipher
+            .encrypt(&Default::default(), file_key.as_slice())
+            .map_err(|_| anyhow::anyhow!("Failed to wrap file key"))?;
+        header.extend_from_slice(ephemeral_public.as_bytes());
+        header.extend_from_slice(&wrapped);
+    }
+    writer.write_all(&header)?;
+    writer.write_all(header_mac(&file_key, &header).as_bytes())?;
+
+    process_encrypt(
+        reader,
+        writer,
+        &Blake3::new(file_key),
+        TextCipher::XChaCha20Poly1305,
+    )
+}
+
+fn open(mut reader: impl Read, writer: impl Write, identity: &StaticSecret) -> Result<u64> {
+    let mut fixed = [0u8; MAGIC.len() + 3];
+    reader
+        .read_exact(&mut fixed)
+        .map_err(|_| anyhow::anyhow!("Input is too short to be a sealed message"))?;
+    if &fixed[..MAGIC.len()] != MAGIC {
+        return Err(anyhow::anyhow!("Input is not an rcli sealed message"));
+    }
+    if fixed[MAGIC.len()] != VERSION {
+        return Err(anyhow::anyhow!(
+            "Unsupported sealed message version: {}",
+            fixed[MAGIC.len()]
+        ));
+    }
+    let count = u16::from_be_bytes([fixed[MAGIC.len() + 1], fixed[MAGIC.len() + 2]]) as usize;
+    let mut stanzas = vec![0u8; count * STANZA_LEN];
+    reader.read_exact(&mut stanzas)?;
+    let mut mac = [0u8; MAC_LEN];
+    reader.read_exact(&mut mac)?;
+
+    let public = PublicKey::from(identity);
+    let file_key = stanzas
+        .chunks_exact(STANZA_LEN)
+        .find_map(|stanza| unwrap_file_key(stanza, identity, &public))
+        .ok_or_else(|| anyhow::anyhow!("The identity is not a recipient of this message"))?;
+
+    let header = [fixed.as_slice(), &stanzas].concat();
+    // blake3::Hash compares in constant time
+    if header_mac(&file_key, &header) != blake3::Hash::from(mac) {
+        return Err(anyhow::anyhow!(
+            "Sealed message header has been tampered with"
+        ));
+    }
+    process_decrypt(reader, writer, &Blake3::new(file_key))
+}
+
+fn unwrap_file_key(
+    stanza: &[u8],
+    identity: &StaticSecret,
+    public: &PublicKey,
+) -> Option<[u8; KEY_LEN]> {
+    let (ephemeral_public, wrapped) = stanza.split_at(KEY_LEN);
+    let ephemeral_public = PublicKey::from(<[u8; KEY_LEN]>::try_from(ephemeral_public).ok()?);
+    let shared = identity.diffie_hellman(&ephemeral_public);
+    if !shared.was_contributory() {
+        return None;
+    }
+    let cipher = wrap_cipher(shared.as_bytes(), &ephemeral_public, public).ok()?;
+    let file_key = cipher.decrypt(&Default::default(), wrapped).ok()?;
+    file_key.try_into().ok()
+}
+
+/// Key wrapping cipher for one recipient, the key is unique per stanza so a zero nonce is safe
+fn wrap_cipher(
+    shared: &[u8; KEY_LEN],
+    ephemeral_public: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<ChaCha20Poly1305> {
+    let salt = [ephemeral_public.as_bytes().as_slice(), recipient.as_bytes()].concat();
+    let mut key = [0u8; KEY_LEN];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(WRAP_INFO, &mut key)
+        .map_err(|_| anyhow::anyhow!("Failed to derive wrapping key"))?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+fn header_mac(file_key: &[u8; KEY_LEN], header: &[u8]) -> blake3::Hash {
+    let key = blake3::derive_key(MAC_CONTEXT, file_key);
+    blake3::keyed_hash(&key, header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::key_format::{decode_x25519_public, decode_x25519_secret};
+
+    fn secret(path: &str) -> StaticSecret {
+        decode_x25519_secret(&std::fs::read(path).unwrap()).unwrap()
+    }
+
+    fn recipients() -> Vec<PublicKey> {
+        ["fixtures/x25519_pk.pem", "fixtures/x25519_pk2.pem"]
+            .iter()
+            .map(|path| decode_x25519_public(&std::fs::read(path).unwrap()).unwrap())
+            .collect()
+    }
+
+    fn open_with(data: &[u8], path: &str) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        process_open(data, &mut out, &secret(path))?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_seal_open_multiple_recipients() {
+        let msg = b"shared team secret";
+        for armor in [false, true] {
+            let mut sealed = Vec::new();
+            process_seal(msg.as_slice(), &mut sealed, &recipients(), armor).unwrap();
+            assert_eq!(sealed.starts_with(ARMOR_HEADER.as_bytes()), armor);
+            for path in ["fixtures/x25519_sk.pem", "fixtures/x25519_sk2.pem"] {
+                assert_eq!(open_with(&sealed, path).unwrap(), msg);
+            }
+        }
+    }
+
+    #[test]
+    fn test_open_with_other_identity_should_fail() {
+        let mut sealed = Vec::new();
+        process_seal(b"hello".as_slice(), &mut sealed, &recipients()[..1], false).unwrap();
+        assert!(open_with(&sealed, "fixtures/x25519_sk2.pem").is_err());
+    }
+
+    #[test]
+    fn test_tampered_stanza_should_fail() {
+        let mut sealed = Vec::new();
+        process_seal(b"hello".as_slice(), &mut sealed, &recipients(), false).unwrap();
+        // Corrupt the stanza of the second recipient, the first one must notice through the MAC
+        sealed[MAGIC.len() + 3 + STANZA_LEN + 1] ^= 1;
+        assert!(open_with(&sealed, "fixtures/x25519_sk.pem").is_err());
+    }
+}
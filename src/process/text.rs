@@ -1,12 +1,36 @@
-use crate::cli::text::TextSignFormat;
-use crate::process::gen_pass::generate_password;
+use crate::cli::base64::Base64Format;
+use crate::cli::codec::CodecFormat;
+use crate::cli::text::{EcdsaSigFormat, KeyFormat, SigEncoding, TextSignFormat};
+use crate::process::base64::process_decode;
+use crate::process::codec::{decode_bytes, encode_bytes};
+use crate::process::key_encryption::{read_passphrase, read_secret_key_file};
+use crate::process::key_format::{
+    decode_ec_public_key, decode_ec_secret_key, decode_signing_key, decode_verifying_key,
+    decode_x25519_public, decode_x25519_secret, encode_ec_public_key_pem, encode_ec_secret_key_pem,
+    encode_signing_key_pem, encode_verifying_key_pem, encode_x25519_public_pem,
+    encode_x25519_secret_pem,
+};
+use crate::process::minisign::{
+    decode_minisign_public_key, decode_minisign_secret_key, encode_minisign_public_key,
+    encode_minisign_secret_key, format_key_id, is_minisign, minisign_key_id, minisign_sign,
+    minisign_verify, MinisignSignature, MINISIGN_KEY_ID_LEN,
+};
+use crate::process::verify::{VerifyError, VerifyFailure, WithFailure};
 use crate::utils::get_reader;
 use anyhow::Result;
-use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD};
 use base64::Engine;
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
+use p256::ecdsa::signature::{DigestSigner, DigestVerifier};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
 use rand::rngs::OsRng;
-use std::io::Read;
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use subtle::ConstantTimeEq;
+use x25519_dalek::{PublicKey, StaticSecret};
 
 pub trait TextSigner {
     fn sign(&self, reader: impl Read) -> Result<Vec<u8>>;
@@ -16,6 +40,17 @@ pub trait TextVerifier {
     fn verify(&self, reader: impl Read, sig: &[u8]) -> Result<bool>;
 }
 
+/// Identify a key without revealing it, signature envelopes and the keyring refer to keys this way
+pub trait KeyFingerprint {
+    /// Hex BLAKE3 hash of the public key
+    fn fingerprint(&self) -> String;
+
+    /// Short key id, a prefix of the fingerprint
+    fn key_id(&self) -> String {
+        self.fingerprint()[..KEY_ID_LEN].to_string()
+    }
+}
+
 pub trait KeyGenerator {
     fn generate_key(&self) -> Result<Key>;
 }
@@ -25,16 +60,64 @@ pub enum Key {
     Asymmetric { public: Vec<u8>, secret: Vec<u8> },
 }
 
+/// Number of hex characters of a fingerprint kept in a key id
+pub const KEY_ID_LEN: usize = 16;
+
+const SYMMETRIC_FINGERPRINT_CONTEXT: &str = "rcli 2024-06-01 symmetric key fingerprint";
+
 pub struct Blake3 {
     key: [u8; 32],
 }
 
+/// HMAC-SHA256/512 signer and verifier, as used by webhook providers
+pub struct HmacSigner {
+    key: Vec<u8>,
+    digest: HmacDigest,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HmacDigest {
+    Sha256,
+    Sha512,
+}
+
+/// ECDSA over P-256 or secp256k1 with SHA-256, signatures are DER encoded
+pub struct EcdsaSigner {
+    key: EcdsaSigningKey,
+}
+
+pub struct EcdsaVerifier {
+    key: EcdsaVerifyingKey,
+}
+
+enum EcdsaSigningKey {
+    P256(p256::ecdsa::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+enum EcdsaVerifyingKey {
+    P256(p256::ecdsa::VerifyingKey),
+    Secp256k1(k256::ecdsa::VerifyingKey),
+}
+
+/// Ed25519 signer, signatures are either bare or minisign `.minisig` files
 pub struct Ed25519Signer {
     key: SigningKey,
+    minisign_key_id: [u8; MINISIGN_KEY_ID_LEN],
 }
 
 pub struct Ed25519Verifier {
     key: VerifyingKey,
+    minisign_key_id: [u8; MINISIGN_KEY_ID_LEN],
+}
+
+/// Ed25519ph (RFC 8032) signer, the input is streamed through SHA-512 instead of being loaded in memory
+pub struct Ed25519phSigner {
+    key: SigningKey,
+}
+
+pub struct Ed25519phVerifier {
+    key: VerifyingKey,
 }
 
 impl Blake3 {
@@ -42,62 +125,315 @@ impl Blake3 {
         Self { key }
     }
 
+    /// Build a key from 32 raw bytes or 64 hex characters
     pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
-        let key = key.as_ref().try_into()?;
-        Ok(Self { key })
+        let key = key.as_ref();
+        if key.len() == blake3::KEY_LEN {
+            return Ok(Self::new(key.try_into()?));
+        }
+        let hex = key.trim_ascii();
+        if hex.len() == 2 * blake3::KEY_LEN {
+            let key: [u8; 32] = decode_bytes(hex, CodecFormat::Hex)?
+                .try_into()
+                .map_err(|_| {
+                    anyhow::anyhow!("Invalid blake3 key: the hex key must decode to 32 bytes")
+                })?;
+            return Ok(Self::new(key));
+        }
+        Err(anyhow::anyhow!(
+            "Invalid blake3 key: expected 32 bytes or 64 hex characters, use a derive context for other keys"
+        ))
+    }
+
+    /// Derive a key from material of any length (a passphrase or another key) with BLAKE3 `derive_key`
+    pub fn derive(context: &str, material: &[u8]) -> Self {
+        Self::new(blake3::derive_key(context, material))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
     }
 
+    /// Load a key file, passphrase protected keys are decrypted first
     pub fn from_file(key_file: &str) -> Result<Self> {
-        let key = std::fs::read(key_file)?;
-        Self::try_new(&key)
+        Self::from_file_with_context(key_file, None)
+    }
+
+    /// Load a key file, with a context its whole content is used as material for [`Blake3::derive`]
+    pub fn from_file_with_context(key_file: &str, context: Option<&str>) -> Result<Self> {
+        let key = read_secret_key_file(key_file)?;
+        match context {
+            Some(context) => Ok(Self::derive(context, &key)),
+            None => Self::try_new(&key),
+        }
+    }
+}
+
+impl KeyFingerprint for Blake3 {
+    fn fingerprint(&self) -> String {
+        symmetric_key_fingerprint(&self.key)
     }
 }
 
 impl TextSigner for Blake3 {
-    fn sign(&self, mut reader: impl Read) -> Result<Vec<u8>> {
+    fn sign(&self, reader: impl Read) -> Result<Vec<u8>> {
         let mut hasher = blake3::Hasher::new_keyed(&self.key);
-        let mut buffer = [0; 1024];
-        loop {
-            let n = reader.read(&mut buffer)?;
-            if n == 0 {
-                break;
-            }
-            hasher.update(&buffer[..n]);
-        }
+        read_chunks(reader, |chunk| {
+            hasher.update(chunk);
+        })?;
         let hash = hasher.finalize();
         Ok(hash.as_bytes().to_vec())
     }
 }
 
 impl TextVerifier for Blake3 {
-    fn verify(&self, mut reader: impl Read, sig: &[u8]) -> Result<bool> {
+    fn verify(&self, reader: impl Read, sig: &[u8]) -> Result<bool> {
         let mut hasher = blake3::Hasher::new_keyed(&self.key);
-        let mut buffer = [0; 1024];
-        loop {
-            let n = reader.read(&mut buffer)?;
-            if n == 0 {
-                break;
+        read_chunks(reader, |chunk| {
+            hasher.update(chunk);
+        })?;
+        let hash = hasher.finalize();
+        Ok(hash.as_bytes().ct_eq(sig).into())
+    }
+}
+
+impl HmacSigner {
+    pub fn sha256(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            digest: HmacDigest::Sha256,
+        }
+    }
+
+    pub fn sha512(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            digest: HmacDigest::Sha512,
+        }
+    }
+
+    /// Load a shared secret of any length. A trailing line break is not part of a text (e.g. hex)
+    /// key, raw binary keys are used byte for byte
+    pub fn from_file(key_file: &str, format: TextSignFormat) -> Result<Self> {
+        let key = strip_text_line_break(read_secret_key_file(key_file)?);
+        match format {
+            TextSignFormat::HmacSha256 => Ok(Self::sha256(key)),
+            TextSignFormat::HmacSha512 => Ok(Self::sha512(key)),
+            _ => Err(anyhow::anyhow!("{} is not an HMAC format", format)),
+        }
+    }
+}
+
+/// Drop the line break an editor or `echo` appends to a text key, keeping any other key as is
+fn strip_text_line_break(mut key: Vec<u8>) -> Vec<u8> {
+    let text = key
+        .strip_suffix(b"\r\n")
+        .or_else(|| key.strip_suffix(b"\n"))
+        .filter(|text| std::str::from_utf8(text).is_ok_and(|s| !s.chars().any(char::is_control)));
+    if let Some(len) = text.map(<[u8]>::len) {
+        key.truncate(len);
+    }
+    key
+}
+
+impl KeyFingerprint for HmacSigner {
+    fn fingerprint(&self) -> String {
+        symmetric_key_fingerprint(&self.key)
+    }
+}
+
+/// Feed `reader` into a MAC
+fn mac_reader<M: Mac>(mut mac: M, reader: impl Read) -> Result<M> {
+    read_chunks(reader, |chunk| mac.update(chunk))?;
+    Ok(mac)
+}
+
+impl TextSigner for HmacSigner {
+    fn sign(&self, reader: impl Read) -> Result<Vec<u8>> {
+        let sig = match self.digest {
+            HmacDigest::Sha256 => {
+                let mac = Hmac::<Sha256>::new_from_slice(&self.key)?;
+                mac_reader(mac, reader)?.finalize().into_bytes().to_vec()
+            }
+            HmacDigest::Sha512 => {
+                let mac = Hmac::<Sha512>::new_from_slice(&self.key)?;
+                mac_reader(mac, reader)?.finalize().into_bytes().to_vec()
+            }
+        };
+        Ok(sig)
+    }
+}
+
+impl TextVerifier for HmacSigner {
+    fn verify(&self, reader: impl Read, sig: &[u8]) -> Result<bool> {
+        // verify_slice compares in constant time
+        let verified = match self.digest {
+            HmacDigest::Sha256 => {
+                let mac = Hmac::<Sha256>::new_from_slice(&self.key)?;
+                mac_reader(mac, reader)?.verify_slice(sig).is_ok()
+            }
+            HmacDigest::Sha512 => {
+                let mac = Hmac::<Sha512>::new_from_slice(&self.key)?;
+                mac_reader(mac, reader)?.verify_slice(sig).is_ok()
             }
-            hasher.update(&buffer[..n]);
+        };
+        Ok(verified)
+    }
+}
+
+impl EcdsaSigner {
+    /// Load a PEM, DER or raw secret key for the curve of `format`
+    pub fn from_file(key_file: &str, format: TextSignFormat) -> Result<Self> {
+        let key = read_secret_key_file(key_file)?;
+        let key = match format {
+            TextSignFormat::EcdsaP256 => {
+                EcdsaSigningKey::P256(decode_ec_secret_key::<p256::NistP256>(&key)?.into())
+            }
+            TextSignFormat::EcdsaSecp256k1 => {
+                EcdsaSigningKey::Secp256k1(decode_ec_secret_key::<k256::Secp256k1>(&key)?.into())
+            }
+            _ => return Err(anyhow::anyhow!("{} is not an ECDSA format", format)),
+        };
+        Ok(Self { key })
+    }
+}
+
+impl KeyFingerprint for EcdsaSigner {
+    fn fingerprint(&self) -> String {
+        let public = match &self.key {
+            EcdsaSigningKey::P256(key) => key.verifying_key().to_encoded_point(true).to_bytes(),
+            EcdsaSigningKey::Secp256k1(key) => {
+                key.verifying_key().to_encoded_point(true).to_bytes()
+            }
+        };
+        public_key_fingerprint(&public)
+    }
+}
+
+impl TextSigner for EcdsaSigner {
+    fn sign(&self, reader: impl Read) -> Result<Vec<u8>> {
+        let mut hasher = Sha256::new();
+        read_chunks(reader, |chunk| hasher.update(chunk))?;
+        let sig = match &self.key {
+            EcdsaSigningKey::P256(key) => {
+                let sig: p256::ecdsa::Signature = key.try_sign_digest(hasher)?;
+                sig.to_der().as_bytes().to_vec()
+            }
+            EcdsaSigningKey::Secp256k1(key) => {
+                let sig: k256::ecdsa::Signature = key.try_sign_digest(hasher)?;
+                sig.to_der().as_bytes().to_vec()
+            }
+        };
+        Ok(sig)
+    }
+}
+
+impl EcdsaVerifier {
+    /// Load a PEM, DER or SEC1 encoded public key for the curve of `format`
+    pub fn from_file(key_file: &str, format: TextSignFormat) -> Result<Self> {
+        let key = std::fs::read(key_file)?;
+        let key = match format {
+            TextSignFormat::EcdsaP256 => {
+                EcdsaVerifyingKey::P256(decode_ec_public_key::<p256::NistP256>(&key)?.into())
+            }
+            TextSignFormat::EcdsaSecp256k1 => {
+                EcdsaVerifyingKey::Secp256k1(decode_ec_public_key::<k256::Secp256k1>(&key)?.into())
+            }
+            _ => return Err(anyhow::anyhow!("{} is not an ECDSA format", format)),
+        };
+        Ok(Self { key })
+    }
+}
+
+impl KeyFingerprint for EcdsaVerifier {
+    fn fingerprint(&self) -> String {
+        let public = match &self.key {
+            EcdsaVerifyingKey::P256(key) => key.to_encoded_point(true).to_bytes(),
+            EcdsaVerifyingKey::Secp256k1(key) => key.to_encoded_point(true).to_bytes(),
+        };
+        public_key_fingerprint(&public)
+    }
+}
+
+impl TextVerifier for EcdsaVerifier {
+    /// Signatures are accepted DER encoded or as raw 64 byte `r || s`
+    fn verify(&self, reader: impl Read, sig: &[u8]) -> Result<bool> {
+        let mut hasher = Sha256::new();
+        read_chunks(reader, |chunk| hasher.update(chunk))?;
+        let verified = match &self.key {
+            EcdsaVerifyingKey::P256(key) => {
+                let sig = p256::ecdsa::Signature::from_slice(sig)
+                    .or_else(|_| p256::ecdsa::Signature::from_der(sig))?;
+                key.verify_digest(hasher, &sig).is_ok()
+            }
+            EcdsaVerifyingKey::Secp256k1(key) => {
+                let sig = k256::ecdsa::Signature::from_slice(sig)
+                    .or_else(|_| k256::ecdsa::Signature::from_der(sig))?;
+                key.verify_digest(hasher, &sig).is_ok()
+            }
+        };
+        Ok(verified)
+    }
+}
+
+/// Convert a DER ECDSA signature produced by [`EcdsaSigner`] to the requested encoding
+pub fn convert_ecdsa_signature(
+    sig: &[u8],
+    format: TextSignFormat,
+    sig_format: EcdsaSigFormat,
+) -> Result<Vec<u8>> {
+    match (format, sig_format) {
+        (_, EcdsaSigFormat::Der) => Ok(sig.to_vec()),
+        (TextSignFormat::EcdsaP256, EcdsaSigFormat::Raw) => {
+            Ok(p256::ecdsa::Signature::from_der(sig)?.to_bytes().to_vec())
         }
-        let hash = hasher.finalize();
-        Ok(hash.as_bytes() == sig)
+        (TextSignFormat::EcdsaSecp256k1, EcdsaSigFormat::Raw) => {
+            Ok(k256::ecdsa::Signature::from_der(sig)?.to_bytes().to_vec())
+        }
+        _ => Err(anyhow::anyhow!(
+            "Raw signatures are only supported for ECDSA formats"
+        )),
     }
 }
 
 impl Ed25519Signer {
     pub fn new(key: SigningKey) -> Self {
-        Self { key }
+        let minisign_key_id = minisign_key_id(&key.verifying_key());
+        Self {
+            key,
+            minisign_key_id,
+        }
     }
+
     pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
-        Ok(Self {
-            key: SigningKey::from_bytes(key.as_ref().try_into()?),
-        })
+        Ok(Self::new(SigningKey::from_bytes(key.as_ref().try_into()?)))
     }
 
+    /// Load a secret key in raw, PKCS#8 (PEM or DER), OpenSSH or minisign format,
+    /// passphrase protected keys are decrypted first
     pub fn from_file(key_file: &str) -> Result<Self> {
-        let key = std::fs::read(key_file)?;
-        Self::try_new(&key)
+        let key = read_secret_key_file(key_file)?;
+        if is_minisign(&key) {
+            let (minisign_key_id, key) =
+                decode_minisign_secret_key(&key, || read_passphrase(Path::new(key_file)))?;
+            return Ok(Self {
+                key,
+                minisign_key_id,
+            });
+        }
+        Ok(Self::new(decode_signing_key(&key)?))
+    }
+
+    /// Sign in the minisign format, the `.minisig` file content is returned
+    pub fn sign_minisign(&self, reader: impl Read, trusted_comment: &str) -> Result<Vec<u8>> {
+        let sig = minisign_sign(&self.key, self.minisign_key_id, reader, trusted_comment)?;
+        Ok(sig.encode().into_bytes())
+    }
+}
+
+impl KeyFingerprint for Ed25519Signer {
+    fn fingerprint(&self) -> String {
+        public_key_fingerprint(self.key.verifying_key().as_bytes())
     }
 }
 
@@ -115,17 +451,53 @@ impl TextSigner for Ed25519Signer {
 
 impl Ed25519Verifier {
     pub fn new(key: VerifyingKey) -> Self {
-        Self { key }
+        let minisign_key_id = minisign_key_id(&key);
+        Self {
+            key,
+            minisign_key_id,
+        }
     }
 
     pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
-        Ok(Self {
-            key: VerifyingKey::from_bytes(key.as_ref().try_into()?)?,
-        })
+        Ok(Self::new(VerifyingKey::from_bytes(
+            key.as_ref().try_into()?,
+        )?))
     }
+
+    /// Load a public key in raw, SPKI (PEM or DER), OpenSSH or minisign format
     pub fn from_file(key_file: &str) -> Result<Self> {
         let key = std::fs::read(key_file)?;
-        Self::try_new(&key)
+        if is_minisign(&key) {
+            let (minisign_key_id, key) = decode_minisign_public_key(&key)?;
+            return Ok(Self {
+                key,
+                minisign_key_id,
+            });
+        }
+        Ok(Self::new(decode_verifying_key(&key)?))
+    }
+
+    /// Verify a minisign `.minisig` file, including the global signature of its trusted comment
+    pub fn verify_minisign(&self, reader: impl Read, minisig: &[u8]) -> Result<bool> {
+        let sig = MinisignSignature::parse(minisig).with_failure(VerifyFailure::MalformedInput)?;
+        if sig.key_id != self.minisign_key_id {
+            return Err(VerifyError::new(
+                VerifyFailure::KeyError,
+                format!(
+                    "Signature was made with minisign key {}, the given key is {}",
+                    format_key_id(&sig.key_id),
+                    format_key_id(&self.minisign_key_id)
+                ),
+            )
+            .into());
+        }
+        minisign_verify(&self.key, reader, &sig)
+    }
+}
+
+impl KeyFingerprint for Ed25519Verifier {
+    fn fingerprint(&self) -> String {
+        public_key_fingerprint(self.key.as_bytes())
     }
 }
 
@@ -141,37 +513,324 @@ impl TextVerifier for Ed25519Verifier {
     }
 }
 
-pub fn process_sign(input: &str, key: &str, format: TextSignFormat) -> Result<String> {
-    let reader = get_reader(input)?;
-    let signed = match format {
-        TextSignFormat::Blake3 => {
-            let signer = Blake3::from_file(key)?;
-            signer.sign(reader)?
+impl Ed25519phSigner {
+    pub fn new(key: SigningKey) -> Self {
+        Self { key }
+    }
+
+    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
+        Ok(Self::new(Ed25519Signer::try_new(key)?.key))
+    }
+
+    pub fn from_file(key_file: &str) -> Result<Self> {
+        Ok(Self::new(Ed25519Signer::from_file(key_file)?.key))
+    }
+}
+
+impl KeyFingerprint for Ed25519phSigner {
+    fn fingerprint(&self) -> String {
+        public_key_fingerprint(self.key.verifying_key().as_bytes())
+    }
+}
+
+impl TextSigner for Ed25519phSigner {
+    fn sign(&self, reader: impl Read) -> Result<Vec<u8>> {
+        let mut hasher = Sha512::new();
+        read_chunks(reader, |chunk| hasher.update(chunk))?;
+        let sig = self.key.sign_prehashed(hasher, None)?;
+        Ok(sig.to_bytes().to_vec())
+    }
+}
+
+impl Ed25519phVerifier {
+    pub fn new(key: VerifyingKey) -> Self {
+        Self { key }
+    }
+
+    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
+        Ok(Self::new(Ed25519Verifier::try_new(key)?.key))
+    }
+
+    pub fn from_file(key_file: &str) -> Result<Self> {
+        Ok(Self::new(Ed25519Verifier::from_file(key_file)?.key))
+    }
+}
+
+impl KeyFingerprint for Ed25519phVerifier {
+    fn fingerprint(&self) -> String {
+        public_key_fingerprint(self.key.as_bytes())
+    }
+}
+
+impl TextVerifier for Ed25519phVerifier {
+    /// Pure Ed25519 signatures never verify here: Ed25519ph signs with a distinct domain prefix
+    fn verify(&self, reader: impl Read, sig: &[u8]) -> Result<bool> {
+        let mut hasher = Sha512::new();
+        read_chunks(reader, |chunk| hasher.update(chunk))?;
+        let sig = Signature::from_bytes(sig.try_into()?);
+        Ok(self.key.verify_prehashed(hasher, None, &sig).is_ok())
+    }
+}
+
+/// Feed `reader` to `f` chunk by chunk
+fn read_chunks(mut reader: impl Read, mut f: impl FnMut(&[u8])) -> Result<()> {
+    let mut buffer = [0; 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
         }
-        TextSignFormat::Ed25519 => {
+        f(&buffer[..n]);
+    }
+    Ok(())
+}
+
+/// Sign `input`, `context` enables BLAKE3 key derivation and is only valid for the blake3 format
+pub fn process_sign(
+    input: &str,
+    key: &str,
+    format: TextSignFormat,
+    context: Option<&str>,
+) -> Result<Vec<u8>> {
+    let (signed, _) = process_sign_with_key_id(input, key, format, context, |_| Vec::new())?;
+    Ok(signed)
+}
+
+/// Sign `input` like [`process_sign`], also returning the key id of the signing key.
+/// The signed message is `header(key id)` followed by the input.
+pub fn process_sign_with_key_id(
+    input: &str,
+    key: &str,
+    format: TextSignFormat,
+    context: Option<&str>,
+    header: impl FnOnce(&str) -> Vec<u8>,
+) -> Result<(Vec<u8>, String)> {
+    fn sign(
+        signer: impl TextSigner + KeyFingerprint,
+        reader: impl Read,
+        header: impl FnOnce(&str) -> Vec<u8>,
+    ) -> Result<(Vec<u8>, String)> {
+        let key_id = signer.key_id();
+        let reader = Cursor::new(header(&key_id)).chain(reader);
+        Ok((signer.sign(reader)?, key_id))
+    }
+
+    check_context(format, context)?;
+    let reader = get_reader(input)?;
+    match format {
+        TextSignFormat::Blake3 => sign(
+            Blake3::from_file_with_context(key, context)?,
+            reader,
+            header,
+        ),
+        TextSignFormat::Ed25519 => sign(Ed25519Signer::from_file(key)?, reader, header),
+        TextSignFormat::Ed25519ph => sign(Ed25519phSigner::from_file(key)?, reader, header),
+        TextSignFormat::Minisign => {
             let signer = Ed25519Signer::from_file(key)?;
-            signer.sign(reader)?
+            let key_id = signer.key_id();
+            let reader = Cursor::new(header(&key_id)).chain(reader);
+            let sig = signer.sign_minisign(reader, &default_trusted_comment(input))?;
+            Ok((sig, key_id))
         }
+        TextSignFormat::HmacSha256 | TextSignFormat::HmacSha512 => {
+            sign(HmacSigner::from_file(key, format)?, reader, header)
+        }
+        TextSignFormat::EcdsaP256 | TextSignFormat::EcdsaSecp256k1 => {
+            sign(EcdsaSigner::from_file(key, format)?, reader, header)
+        }
+    }
+}
+
+/// Sign `input` as a minisign `.minisig` file, the trusted comment defaults to the time and file name
+pub fn process_sign_minisign(
+    input: &str,
+    key: &str,
+    trusted_comment: Option<&str>,
+) -> Result<Vec<u8>> {
+    let signer = Ed25519Signer::from_file(key)?;
+    let trusted_comment = match trusted_comment {
+        Some(comment) => comment.to_string(),
+        None => default_trusted_comment(input),
     };
+    signer.sign_minisign(get_reader(input)?, &trusted_comment)
+}
 
-    Ok(BASE64_URL_SAFE_NO_PAD.encode(&signed))
+/// Trusted comment minisign writes by default
+fn default_trusted_comment(input: &str) -> String {
+    let timestamp = chrono::Utc::now().timestamp();
+    match Path::new(input).file_name() {
+        Some(name) if input != "-" => format!(
+            "timestamp:{}\tfile:{}\thashed",
+            timestamp,
+            name.to_string_lossy()
+        ),
+        _ => format!("timestamp:{}\thashed", timestamp),
+    }
 }
 
-pub fn process_verify(input: &str, key: &str, sig: &str, format: TextSignFormat) -> Result<bool> {
-    let reader = get_reader(input)?;
-    let sig = BASE64_URL_SAFE_NO_PAD.decode(sig.as_bytes())?;
-    let verified = match format {
-        TextSignFormat::Blake3 => {
-            let verifier = Blake3::from_file(key)?;
-            verifier.verify(reader, &sig)?
-        }
+pub fn process_verify(
+    input: &str,
+    key: &str,
+    sig: &[u8],
+    format: TextSignFormat,
+    context: Option<&str>,
+) -> Result<bool> {
+    verify_with_key_id(input, key, sig, format, context, None, &[])
+}
+
+/// Verify `sig` over `header` followed by the input, with `key_id` the key must also be the one
+/// the signature claims to be made with
+pub(crate) fn verify_with_key_id(
+    input: &str,
+    key: &str,
+    sig: &[u8],
+    format: TextSignFormat,
+    context: Option<&str>,
+    key_id: Option<&str>,
+    header: &[u8],
+) -> Result<bool> {
+    check_context(format, context).with_failure(VerifyFailure::KeyError)?;
+    let reader = get_reader(input).with_failure(VerifyFailure::MalformedInput)?;
+    let reader = header.chain(reader);
+    match format {
+        TextSignFormat::Blake3 => run_verifier(
+            Blake3::from_file_with_context(key, context),
+            reader,
+            sig,
+            key_id,
+        ),
         TextSignFormat::Ed25519 => {
-            let verifier = Ed25519Verifier::from_file(key)?;
-            verifier.verify(reader, &sig)?
+            run_verifier(Ed25519Verifier::from_file(key), reader, sig, key_id)
+        }
+        TextSignFormat::Ed25519ph => {
+            run_verifier(Ed25519phVerifier::from_file(key), reader, sig, key_id)
+        }
+        TextSignFormat::Minisign => {
+            let verifier = Ed25519Verifier::from_file(key).with_failure(VerifyFailure::KeyError)?;
+            check_key_id(&verifier, key_id)?;
+            verifier
+                .verify_minisign(reader, sig)
+                .with_failure(VerifyFailure::MalformedInput)
+        }
+        TextSignFormat::HmacSha256 | TextSignFormat::HmacSha512 => {
+            run_verifier(HmacSigner::from_file(key, format), reader, sig, key_id)
+        }
+        TextSignFormat::EcdsaP256 | TextSignFormat::EcdsaSecp256k1 => {
+            run_verifier(EcdsaVerifier::from_file(key, format), reader, sig, key_id)
+        }
+    }
+}
+
+/// Load errors are key errors, errors while verifying mean the input or signature is malformed
+fn run_verifier(
+    verifier: Result<impl TextVerifier + KeyFingerprint>,
+    reader: impl Read,
+    sig: &[u8],
+    key_id: Option<&str>,
+) -> Result<bool> {
+    let verifier = verifier.with_failure(VerifyFailure::KeyError)?;
+    check_key_id(&verifier, key_id)?;
+    verifier
+        .verify(reader, sig)
+        .with_failure(VerifyFailure::MalformedInput)
+}
+
+fn check_key_id(key: &impl KeyFingerprint, expected: Option<&str>) -> Result<()> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(&key.key_id()) => Err(VerifyError::new(
+            VerifyFailure::KeyError,
+            format!(
+                "Signature was made with key {}, the given key is {}",
+                expected,
+                key.key_id()
+            ),
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+fn check_context(format: TextSignFormat, context: Option<&str>) -> Result<()> {
+    match (format, context) {
+        (TextSignFormat::Blake3, _) | (_, None) => Ok(()),
+        _ => Err(anyhow::anyhow!(
+            "A derive context is only supported with the blake3 format"
+        )),
+    }
+}
+
+/// Fingerprint of a key file, `secret` tells whether it holds the secret or the public half
+///
+/// Both halves of a key pair have the same fingerprint, symmetric keys are always secret.
+pub fn key_file_fingerprint(key_file: &str, format: KeyFormat, secret: bool) -> Result<String> {
+    if format.is_symmetric() && !secret {
+        return Err(anyhow::anyhow!("{} keys have no public half", format));
+    }
+    let format = match (format, secret) {
+        (KeyFormat::Sign(format), _) => format,
+        (KeyFormat::X25519, true) => {
+            let key = decode_x25519_secret(&read_secret_key_file(key_file)?)?;
+            return Ok(public_key_fingerprint(PublicKey::from(&key).as_bytes()));
+        }
+        (KeyFormat::X25519, false) => {
+            let key = decode_x25519_public(&std::fs::read(key_file)?)?;
+            return Ok(public_key_fingerprint(key.as_bytes()));
         }
     };
+    let fingerprint = match (format, secret) {
+        (TextSignFormat::Blake3, _) => Blake3::from_file(key_file)?.fingerprint(),
+        (TextSignFormat::HmacSha256 | TextSignFormat::HmacSha512, _) => {
+            HmacSigner::from_file(key_file, format)?.fingerprint()
+        }
+        (TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph | TextSignFormat::Minisign, true) => {
+            Ed25519Signer::from_file(key_file)?.fingerprint()
+        }
+        (TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph | TextSignFormat::Minisign, false) => {
+            Ed25519Verifier::from_file(key_file)?.fingerprint()
+        }
+        (TextSignFormat::EcdsaP256 | TextSignFormat::EcdsaSecp256k1, true) => {
+            EcdsaSigner::from_file(key_file, format)?.fingerprint()
+        }
+        (TextSignFormat::EcdsaP256 | TextSignFormat::EcdsaSecp256k1, false) => {
+            EcdsaVerifier::from_file(key_file, format)?.fingerprint()
+        }
+    };
+    Ok(fingerprint)
+}
 
-    Ok(verified)
+/// Fingerprint of an encoded public key
+pub fn public_key_fingerprint(public: &[u8]) -> String {
+    blake3::hash(public).to_hex().to_string()
+}
+
+/// Fingerprint of a shared secret, hashed through a derive context so it cannot be brute forced as a MAC
+fn symmetric_key_fingerprint(key: &[u8]) -> String {
+    public_key_fingerprint(&blake3::derive_key(SYMMETRIC_FINGERPRINT_CONTEXT, key))
+}
+
+/// Encode a raw signature for output
+pub fn encode_signature(sig: &[u8], encoding: SigEncoding) -> Vec<u8> {
+    match encoding {
+        SigEncoding::Hex => encode_bytes(sig, CodecFormat::Hex),
+        SigEncoding::Base64 => BASE64_STANDARD.encode(sig).into_bytes(),
+        SigEncoding::Base64Url => BASE64_URL_SAFE_NO_PAD.encode(sig).into_bytes(),
+        SigEncoding::Raw => sig.to_vec(),
+    }
+}
+
+/// Decode a signature back to raw bytes, surrounding whitespace of text encodings is ignored
+pub fn decode_signature(sig: &[u8], encoding: SigEncoding) -> Result<Vec<u8>> {
+    let base64 = |format| -> Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        process_decode(sig, &mut decoded, format, true)?;
+        Ok(decoded)
+    };
+    match encoding {
+        SigEncoding::Hex => decode_bytes(sig, CodecFormat::Hex),
+        SigEncoding::Base64 => base64(Base64Format::Standard),
+        SigEncoding::Base64Url => base64(Base64Format::UrlSafe),
+        SigEncoding::Raw => Ok(sig.to_vec()),
+    }
 }
 
 pub struct CommonKeyGenerator {
@@ -190,12 +849,13 @@ impl CommonKeyGenerator {
 
 impl KeyGenerator for CommonKeyGenerator {
     fn generate_key(&self) -> Result<Key> {
-        let key = generate_password(self.key_length as u8, true, true, true, true)?;
         match self.key_format {
-            TextSignFormat::Blake3 => Ok(Key::Symmetric {
-                key: key.into_bytes(),
-            }),
-            TextSignFormat::Ed25519 => {
+            TextSignFormat::Blake3 | TextSignFormat::HmacSha256 | TextSignFormat::HmacSha512 => {
+                let mut key = vec![0u8; self.key_length];
+                OsRng.fill_bytes(&mut key);
+                Ok(Key::Symmetric { key })
+            }
+            TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph | TextSignFormat::Minisign => {
                 let mut rng = OsRng;
                 let key = SigningKey::generate(&mut rng);
                 let public = key.verifying_key().to_bytes().to_vec();
@@ -204,18 +864,83 @@ impl KeyGenerator for CommonKeyGenerator {
                     secret: key.to_bytes().to_vec(),
                 })
             }
+            TextSignFormat::EcdsaP256 => {
+                let key = p256::SecretKey::random(&mut OsRng);
+                Ok(Key::Asymmetric {
+                    public: key.public_key().to_encoded_point(true).as_bytes().to_vec(),
+                    secret: key.to_bytes().to_vec(),
+                })
+            }
+            TextSignFormat::EcdsaSecp256k1 => {
+                let key = k256::SecretKey::random(&mut OsRng);
+                Ok(Key::Asymmetric {
+                    public: key.public_key().to_encoded_point(true).as_bytes().to_vec(),
+                    secret: key.to_bytes().to_vec(),
+                })
+            }
         }
     }
 }
 
-pub fn generate_key(format: TextSignFormat) -> Result<Key> {
+/// Generate a key ready to be written to disk, asymmetric keys are encoded as PKCS#8/SPKI PEM
+pub fn generate_key(format: KeyFormat) -> Result<Key> {
+    let format = match format {
+        KeyFormat::Sign(format) => format,
+        KeyFormat::X25519 => {
+            let key = StaticSecret::random_from_rng(OsRng);
+            return Ok(Key::Asymmetric {
+                public: encode_x25519_public_pem(&PublicKey::from(&key))?,
+                secret: encode_x25519_secret_pem(&key)?,
+            });
+        }
+    };
     let key_length = match format {
         TextSignFormat::Blake3 => blake3::KEY_LEN,
-        TextSignFormat::Ed25519 => ed25519_dalek::SECRET_KEY_LENGTH,
+        TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph | TextSignFormat::Minisign => {
+            ed25519_dalek::SECRET_KEY_LENGTH
+        }
+        // A key as long as the hash block size is the most HMAC makes use of
+        TextSignFormat::HmacSha256 => 64,
+        TextSignFormat::HmacSha512 => 128,
+        TextSignFormat::EcdsaP256 | TextSignFormat::EcdsaSecp256k1 => 32,
     };
     let generator = CommonKeyGenerator::new(key_length, format);
 
-    generator.generate_key()
+    match (format, generator.generate_key()?) {
+        (TextSignFormat::EcdsaP256, Key::Asymmetric { secret, .. }) => {
+            let key = decode_ec_secret_key::<p256::NistP256>(&secret)?;
+            Ok(Key::Asymmetric {
+                public: encode_ec_public_key_pem(&key.public_key())?,
+                secret: encode_ec_secret_key_pem(&key)?,
+            })
+        }
+        (TextSignFormat::EcdsaSecp256k1, Key::Asymmetric { secret, .. }) => {
+            let key = decode_ec_secret_key::<k256::Secp256k1>(&secret)?;
+            Ok(Key::Asymmetric {
+                public: encode_ec_public_key_pem(&key.public_key())?,
+                secret: encode_ec_secret_key_pem(&key)?,
+            })
+        }
+        (TextSignFormat::Minisign, _) => generate_minisign_key(None),
+        (_, Key::Asymmetric { secret, .. }) => {
+            let key = SigningKey::from_bytes(secret.as_slice().try_into()?);
+            Ok(Key::Asymmetric {
+                public: encode_verifying_key_pem(&key.verifying_key())?,
+                secret: encode_signing_key_pem(&key)?,
+            })
+        }
+        (_, key) => Ok(key),
+    }
+}
+
+/// Generate a minisign key pair, with a passphrase the secret key is encrypted the way minisign does it
+pub fn generate_minisign_key(passphrase: Option<&str>) -> Result<Key> {
+    let key = SigningKey::generate(&mut OsRng);
+    let key_id = minisign_key_id(&key.verifying_key());
+    Ok(Key::Asymmetric {
+        public: encode_minisign_public_key(key_id, &key.verifying_key()).into_bytes(),
+        secret: encode_minisign_secret_key(key_id, &key, passphrase)?.into_bytes(),
+    })
 }
 
 #[cfg(test)]
@@ -233,6 +958,71 @@ mod tests {
         assert!(verifier.verify(Cursor::new(msg), &sig).unwrap());
     }
 
+    #[test]
+    fn test_hmac_sign_verify() {
+        // RFC 4231 test case 2
+        let msg = b"what do ya want for nothing?";
+        let signer = HmacSigner::sha256(b"Jefe".to_vec());
+        let sig = signer.sign(Cursor::new(msg)).unwrap();
+        assert_eq!(
+            encode_signature(&sig, SigEncoding::Hex),
+            b"5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert!(signer.verify(Cursor::new(msg), &sig).unwrap());
+        assert!(!signer.verify(Cursor::new(msg), &sig[..16]).unwrap());
+
+        let signer = HmacSigner::sha512(b"Jefe".to_vec());
+        let sig = signer.sign(Cursor::new(msg)).unwrap();
+        assert!(encode_signature(&sig, SigEncoding::Hex)
+            .starts_with(b"164b7a7bfcf819e2e395fbe73b56e0a3"));
+        assert!(!signer.verify(Cursor::new(b"tampered"), &sig).unwrap());
+    }
+
+    #[test]
+    fn test_hmac_key_line_break() {
+        assert_eq!(strip_text_line_break(b"c0ffee\n".to_vec()), b"c0ffee");
+        assert_eq!(strip_text_line_break(b"secret\r\n".to_vec()), b"secret");
+        let binary = vec![0x9f, 0x00, 0xd3, b'\r', b'\n'];
+        assert_eq!(strip_text_line_break(binary.clone()), binary);
+
+        let path = std::env::temp_dir().join(format!("rcli-hmac-{}.key", std::process::id()));
+        std::fs::write(&path, &binary).unwrap();
+        let signer = HmacSigner::from_file(path.to_str().unwrap(), TextSignFormat::HmacSha256);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(signer.unwrap().key, binary);
+    }
+
+    #[test]
+    fn test_ecdsa_sign_verify() {
+        let msg = b"hello world";
+        for (format, name) in [
+            (TextSignFormat::EcdsaP256, "p256"),
+            (TextSignFormat::EcdsaSecp256k1, "secp256k1"),
+        ] {
+            let signer =
+                EcdsaSigner::from_file(&format!("fixtures/{}_sk.pem", name), format).unwrap();
+            let verifier =
+                EcdsaVerifier::from_file(&format!("fixtures/{}_pk.pem", name), format).unwrap();
+            let der = signer.sign(Cursor::new(msg)).unwrap();
+            assert!(verifier.verify(Cursor::new(msg), &der).unwrap());
+            assert!(!verifier.verify(Cursor::new(b"tampered"), &der).unwrap());
+
+            let raw = convert_ecdsa_signature(&der, format, EcdsaSigFormat::Raw).unwrap();
+            assert_eq!(raw.len(), 64);
+            assert!(verifier.verify(Cursor::new(msg), &raw).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_ecdsa_verify_openssl_signature() {
+        // openssl dgst -sha256 -sign fixtures/p256_sk.pem fixtures/blake3.key
+        let sig = std::fs::read("fixtures/p256_blake3_key.sig").unwrap();
+        let verifier =
+            EcdsaVerifier::from_file("fixtures/p256_pk.pem", TextSignFormat::EcdsaP256).unwrap();
+        let msg = std::fs::read("fixtures/blake3.key").unwrap();
+        assert!(verifier.verify(Cursor::new(msg), &sig).unwrap());
+    }
+
     #[test]
     fn test_ed25519_sign_verify() {
         let signer = Ed25519Signer::from_file("fixtures/secret.key").unwrap();
@@ -243,6 +1033,137 @@ mod tests {
         assert!(verifier.verify(Cursor::new(msg), &sig).unwrap());
     }
 
+    #[test]
+    fn test_ed25519ph_sign_verify() {
+        let signer = Ed25519phSigner::from_file("fixtures/secret.key").unwrap();
+        let verifier = Ed25519phVerifier::from_file("fixtures/public.key").unwrap();
+
+        let msg = vec![7u8; 10 * 1024 + 3];
+        let sig = signer.sign(Cursor::new(&msg)).unwrap();
+        assert!(verifier.verify(Cursor::new(&msg), &sig).unwrap());
+        assert!(!verifier.verify(Cursor::new(b"hello world"), &sig).unwrap());
+    }
+
+    #[test]
+    fn test_ed25519_pure_and_prehashed_do_not_mix() {
+        let msg = b"hello world";
+        let pure = Ed25519Signer::from_file("fixtures/secret.key")
+            .unwrap()
+            .sign(Cursor::new(msg))
+            .unwrap();
+        let prehashed = Ed25519phSigner::from_file("fixtures/secret.key")
+            .unwrap()
+            .sign(Cursor::new(msg))
+            .unwrap();
+
+        let verifier = Ed25519Verifier::from_file("fixtures/public.key").unwrap();
+        assert!(!verifier.verify(Cursor::new(msg), &prehashed).unwrap());
+        let verifier = Ed25519phVerifier::from_file("fixtures/public.key").unwrap();
+        assert!(!verifier.verify(Cursor::new(msg), &pure).unwrap());
+    }
+
+    #[test]
+    fn test_signature_encoding_roundtrip() {
+        let sig = (0..64u8).collect::<Vec<_>>();
+        for encoding in [
+            SigEncoding::Hex,
+            SigEncoding::Base64,
+            SigEncoding::Base64Url,
+            SigEncoding::Raw,
+        ] {
+            let encoded = encode_signature(&sig, encoding);
+            assert_eq!(decode_signature(&encoded, encoding).unwrap(), sig);
+        }
+        assert_eq!(encode_signature(&[0xab, 0x01], SigEncoding::Hex), b"ab01");
+        assert_eq!(
+            decode_signature(b"AB01\n", SigEncoding::Hex).unwrap(),
+            [0xab, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_process_sign_verify() {
+        let sig = process_sign(
+            "Cargo.toml",
+            "fixtures/secret.key",
+            TextSignFormat::Ed25519,
+            None,
+        )
+        .unwrap();
+        assert!(process_verify(
+            "Cargo.toml",
+            "fixtures/public.key",
+            &sig,
+            TextSignFormat::Ed25519,
+            None
+        )
+        .unwrap());
+        assert!(!process_verify(
+            "README.md",
+            "fixtures/public.key",
+            &sig,
+            TextSignFormat::Ed25519,
+            None
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_blake3_hex_and_derived_keys() {
+        let raw = std::fs::read("fixtures/blake3.key").unwrap();
+        let hex = format!(
+            "{}\n",
+            String::from_utf8(encode_bytes(&raw, CodecFormat::Hex)).unwrap()
+        );
+        let key = Blake3::try_new(hex.as_bytes()).unwrap();
+        assert_eq!(key.as_bytes().as_slice(), raw.as_slice());
+        assert!(Blake3::try_new(b"too short").is_err());
+        let spaced = format!("{}  {}", &hex[..31], &hex[31..62]);
+        assert_eq!(spaced.len(), 64);
+        assert!(Blake3::try_new(spaced.as_bytes()).is_err());
+
+        let derived = Blake3::derive("rcli test", b"correct horse battery staple");
+        assert_ne!(
+            derived.as_bytes(),
+            Blake3::derive("rcli other", b"correct horse battery staple").as_bytes()
+        );
+        let sig = process_sign(
+            "Cargo.toml",
+            "README.md",
+            TextSignFormat::Blake3,
+            Some("rcli test"),
+        )
+        .unwrap();
+        assert!(process_verify(
+            "Cargo.toml",
+            "README.md",
+            &sig,
+            TextSignFormat::Blake3,
+            Some("rcli test")
+        )
+        .unwrap());
+        assert!(process_sign(
+            "Cargo.toml",
+            "fixtures/secret.key",
+            TextSignFormat::Ed25519,
+            Some("rcli test")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_generate_key_pem() {
+        let Key::Asymmetric { public, secret } =
+            generate_key(KeyFormat::Sign(TextSignFormat::Ed25519)).unwrap()
+        else {
+            panic!("Invalid key type");
+        };
+        let signer = Ed25519Signer::new(decode_signing_key(&secret).unwrap());
+        let verifier = Ed25519Verifier::new(decode_verifying_key(&public).unwrap());
+        let sig = signer.sign(Cursor::new(b"hello")).unwrap();
+        assert!(verifier.verify(Cursor::new(b"hello"), &sig).unwrap());
+    }
+
     #[test]
     fn test_key_generator() {
         let generator = CommonKeyGenerator::new(32, TextSignFormat::Blake3);
@@ -250,6 +1171,8 @@ mod tests {
         match key {
             Key::Symmetric { key } => {
                 assert_eq!(key.len(), 32);
+                // Full entropy bytes, a printable-only key is astronomically unlikely
+                assert!(key.iter().any(|b| !b.is_ascii_graphic()));
             }
             _ => panic!("Invalid key type"),
         }
@@ -0,0 +1,147 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Exit codes of the verify commands, printed in their `--help`
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  verified
+  1  invalid signature or token
+  2  invalid command line usage
+  3  malformed input (unreadable input, undecodable signature or token)
+  4  key error (missing, unreadable or unsupported key)";
+
+/// Why a verification failed, each cause maps to its own exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyFailure {
+    Invalid,
+    MalformedInput,
+    KeyError,
+}
+
+impl VerifyFailure {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            VerifyFailure::Invalid => 1,
+            VerifyFailure::MalformedInput => 3,
+            VerifyFailure::KeyError => 4,
+        }
+    }
+}
+
+/// Error returned by the verify commands, `main` exits with its exit code
+#[derive(Debug)]
+pub struct VerifyError {
+    pub failure: VerifyFailure,
+    pub message: String,
+}
+
+impl VerifyError {
+    pub fn new(failure: VerifyFailure, message: impl Into<String>) -> Self {
+        Self {
+            failure,
+            message: message.into(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.failure.exit_code()
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Attach a [`VerifyFailure`] to an error, errors that already carry one are kept as they are
+pub trait WithFailure<T> {
+    fn with_failure(self, failure: VerifyFailure) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> WithFailure<T> for Result<T, E> {
+    fn with_failure(self, failure: VerifyFailure) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            let e = e.into();
+            if e.is::<VerifyError>() {
+                return e;
+            }
+            VerifyError::new(failure, format!("{:#}", e)).into()
+        })
+    }
+}
+
+/// Machine readable result printed by the verify commands with `--json`
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<VerifyFailure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claims: Option<serde_json::Value>,
+    /// Trusted comment of a verified minisign signature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trusted_comment: Option<String>,
+}
+
+impl VerifyReport {
+    pub fn verified(claims: Option<serde_json::Value>) -> Self {
+        Self {
+            verified: true,
+            error: None,
+            message: None,
+            exit_code: 0,
+            claims,
+            trusted_comment: None,
+        }
+    }
+
+    /// Build the report of a failed verification, errors without a cause count as malformed input
+    pub fn failed(error: &anyhow::Error) -> Self {
+        let failure = error
+            .downcast_ref::<VerifyError>()
+            .map_or(VerifyFailure::MalformedInput, |e| e.failure);
+        Self {
+            verified: false,
+            error: Some(failure),
+            message: Some(format!("{:#}", error)),
+            exit_code: failure.exit_code(),
+            claims: None,
+            trusted_comment: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_failure_keeps_first_cause() {
+        let err = Err::<(), _>(anyhow::anyhow!("no such key"))
+            .with_failure(VerifyFailure::KeyError)
+            .with_failure(VerifyFailure::MalformedInput)
+            .unwrap_err();
+        let report = VerifyReport::failed(&err);
+        assert_eq!(report.error, Some(VerifyFailure::KeyError));
+        assert_eq!(report.exit_code, 4);
+        assert_eq!(report.message.as_deref(), Some("no such key"));
+    }
+
+    #[test]
+    fn test_report_json() {
+        let err = anyhow::Error::from(VerifyError::new(VerifyFailure::Invalid, "bad"));
+        let json = serde_json::to_value(VerifyReport::failed(&err)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"verified": false, "error": "invalid", "message": "bad", "exit_code": 1})
+        );
+        let json = serde_json::to_value(VerifyReport::verified(None)).unwrap();
+        assert_eq!(json, serde_json::json!({"verified": true, "exit_code": 0}));
+    }
+}
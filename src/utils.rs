@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
 pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>> {
     let reader: Box<dyn Read> = if input == "-" {
@@ -8,3 +8,12 @@ pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>> {
     };
     Ok(reader)
 }
+
+pub fn get_writer(output: &str) -> anyhow::Result<Box<dyn Write>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(std::fs::File::create(output)?)
+    };
+    Ok(writer)
+}